@@ -0,0 +1,334 @@
+use collada::{Mesh, Skin, Animation, error::*, document::*, transform::*};
+use xml_tree::*;
+use math::{Matrix4, Vector3};
+use std::error::Error;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum UpAxis {
+    // Up is +X, right is -Y and +Z points towards the viewer
+    X,
+    // Up is +Y, right is +X and +Z points towards the viewer
+    Y,
+    // Up is +Z, right is +X and -Y points towards the viewer
+    Z,
+}
+
+impl UpAxis {
+    pub fn from_name(name: &str) -> Option<UpAxis> {
+        match name {
+            "X_UP" => Some(UpAxis::X),
+            "Y_UP" => Some(UpAxis::Y),
+            "Z_UP" => Some(UpAxis::Z),
+            _ => None,
+        }
+    }
+
+    // Rotation from this convention into Y up, rows of a 3x3 matrix
+    fn to_y_up(self) -> [[f32; 3]; 3] {
+        match self {
+            UpAxis::X => [[0.0, -1.0, 0.0], [1.0, 0.0, 0.0], [0.0, 0.0, 1.0]],
+            UpAxis::Y => [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]],
+            UpAxis::Z => [[1.0, 0.0, 0.0], [0.0, 0.0, 1.0], [0.0, -1.0, 0.0]],
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Unit {
+    pub name: String,
+    // Length of one unit in meters
+    pub meter: f32,
+}
+
+impl Default for Unit {
+    fn default() -> Unit {
+        Unit {
+            name: "meter".to_string(),
+            meter: 1.0,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Contributor {
+    pub author: Option<String>,
+    pub authoring_tool: Option<String>,
+    pub comments: Option<String>,
+    pub copyright: Option<String>,
+    pub source_data: Option<String>,
+}
+
+impl Contributor {
+    pub fn parse_contributor(node: &XmlNode, tree: &XmlTree) -> Contributor {
+        let mut contributor = Contributor::default();
+
+        for child in tree.nodes_iter(node.get_children()) {
+            let child = child.unwrap();
+            let value = child.get_characters().map(|characters| characters.trim().to_string());
+
+            match child.name.local_name.as_ref() {
+                "author" => contributor.author = value,
+                "authoring_tool" => contributor.authoring_tool = value,
+                "comments" => contributor.comments = value,
+                "copyright" => contributor.copyright = value,
+                "source_data" => contributor.source_data = value,
+                _ => {}
+            }
+        }
+
+        contributor
+    }
+}
+
+// Metadata of the document. Dates are kept as the ISO 8601 strings of the document
+#[derive(Clone, Debug)]
+pub struct Asset {
+    pub contributors: Vec<Contributor>,
+    pub created: Option<String>,
+    pub modified: Option<String>,
+    pub unit: Unit,
+    pub up_axis: UpAxis,
+}
+
+impl Default for Asset {
+    fn default() -> Asset {
+        Asset {
+            contributors: vec![],
+            created: None,
+            modified: None,
+            unit: Unit::default(),
+            up_axis: UpAxis::Y,
+        }
+    }
+}
+
+impl Asset {
+    pub fn parse_asset(node: &XmlNode, tree: &XmlTree) -> Result<Asset, Box<dyn Error>> {
+        if node.name.local_name != "asset" {
+            return Err(Box::new(AssetParseError));
+        }
+        let mut asset = Asset::default();
+
+        for child in tree.nodes_iter(node.get_children()) {
+            let child = child.unwrap();
+
+            match child.name.local_name.as_ref() {
+                "contributor" => asset.contributors.push(Contributor::parse_contributor(child, tree)),
+                "created" => asset.created = child.get_characters().map(|created| created.trim().to_string()),
+                "modified" => asset.modified = child.get_characters().map(|modified| modified.trim().to_string()),
+                "unit" => {
+                    let default = Unit::default();
+                    let name = child.get_attribute_with_name("name").map(|name| name.to_string()).unwrap_or(default.name);
+                    let meter = match child.get_attribute_with_name("meter") {
+                        Some(meter) => meter.trim().parse()?,
+                        None => default.meter,
+                    };
+                    asset.unit = Unit { name, meter };
+                }
+                "up_axis" => {
+                    let up_axis = child.get_characters().ok_or(AssetParseError)?;
+                    asset.up_axis = UpAxis::from_name(up_axis.trim()).ok_or(AssetParseError)?;
+                }
+                _ => {}
+            }
+        }
+
+        Ok(asset)
+    }
+}
+
+// Change of axis convention and unit. Points are rotated and scaled, directions are only rotated
+// and matrices are conjugated so they work on converted points
+#[derive(Copy, Clone, Debug)]
+struct Conversion {
+    rotation: [[f32; 3]; 3],
+    scale: f32,
+}
+
+impl Conversion {
+    fn new(from: &Asset, up_axis: UpAxis, meter: f32) -> Conversion {
+        let to_y_up = from.up_axis.to_y_up();
+        // The rotations are orthogonal so the inverse is the transpose
+        let from_y_up = up_axis.to_y_up();
+
+        let mut rotation = [[0.0; 3]; 3];
+        for (i, row) in rotation.iter_mut().enumerate() {
+            for (j, value) in row.iter_mut().enumerate() {
+                *value = (0..3).map(|k| from_y_up[k][i] * to_y_up[k][j]).sum();
+            }
+        }
+
+        Conversion {
+            rotation,
+            scale: from.unit.meter / meter,
+        }
+    }
+
+    fn direction(&self, vector: Vector3) -> Vector3 {
+        let r = &self.rotation;
+
+        Vector3 {
+            x: r[0][0] * vector.x + r[0][1] * vector.y + r[0][2] * vector.z,
+            y: r[1][0] * vector.x + r[1][1] * vector.y + r[1][2] * vector.z,
+            z: r[2][0] * vector.x + r[2][1] * vector.y + r[2][2] * vector.z,
+        }
+    }
+
+    fn point(&self, point: Vector3) -> Vector3 {
+        let point = self.direction(point);
+
+        Vector3 { x: point.x * self.scale, y: point.y * self.scale, z: point.z * self.scale }
+    }
+
+    fn matrix(&self, scale: f32, transpose: bool) -> Matrix4 {
+        let mut matrix = Matrix4::identity();
+        for i in 0..3 {
+            for j in 0..3 {
+                let value = if transpose { self.rotation[j][i] } else { self.rotation[i][j] };
+                matrix.values[i * 4 + j] = value * scale;
+            }
+        }
+
+        matrix
+    }
+
+    fn conjugate(&self, matrix: Matrix4) -> Matrix4 {
+        self.matrix(self.scale, false) * matrix * self.matrix(1.0 / self.scale, true)
+    }
+
+    fn mesh(&self, mesh: &mut Mesh) {
+        for vertex in mesh.vertices.iter_mut() {
+            *vertex = self.point(*vertex);
+        }
+
+        let directions = mesh.tangents.iter_mut()
+            .chain(mesh.binormals.iter_mut())
+            .flat_map(|set| set.values.iter_mut())
+            .chain(mesh.normals.iter_mut());
+        for direction in directions {
+            *direction = self.direction(*direction);
+        }
+    }
+
+    fn transformation(&self, transformation: Transformation) -> Transformation {
+        let array = |vector: Vector3| [vector.x, vector.y, vector.z];
+        let vector = |array: &[f32]| Vector3 { x: array[0], y: array[1], z: array[2] };
+
+        match transformation {
+            Transformation::Matrix(matrix) => Transformation::Matrix(self.conjugate(matrix)),
+            Transformation::Translate(translation) => Transformation::Translate(array(self.point(vector(&translation)))),
+            Transformation::Rotate([x, y, z, angle]) => {
+                let axis = self.direction(Vector3 { x, y, z });
+                Transformation::Rotate([axis.x, axis.y, axis.z, angle])
+            }
+            // Axis conversions only swap and negate axes so the scale factors move to their new axes
+            Transformation::Scale(scale) => {
+                let mut converted = [0.0; 3];
+                for (i, row) in self.rotation.iter().enumerate() {
+                    converted[i] = (0..3).map(|j| row[j].abs() * scale[j]).sum();
+                }
+                Transformation::Scale(converted)
+            }
+            // Converting the points of a lookat would keep the local axes of the node in the old
+            // convention, the conjugated matrix also converts the axes
+            Transformation::LookAt(_) => Transformation::Matrix(self.conjugate(transformation.matrix())),
+            Transformation::Skew(values) => {
+                let rotation = self.direction(vector(&values[1..4]));
+                let translation = self.direction(vector(&values[4..7]));
+                Transformation::Skew([values[0], rotation.x, rotation.y, rotation.z, translation.x, translation.y, translation.z])
+            }
+        }
+    }
+
+    fn node(&self, node: &mut Node) {
+        node.matrix = self.conjugate(node.matrix);
+        for transform in node.transforms.iter_mut() {
+            transform.transformation = self.transformation(transform.transformation);
+        }
+
+        for node in node.children.iter_mut() {
+            self.node(node);
+        }
+    }
+
+    fn skin(&self, skin: &mut Skin) {
+        skin.bind_shape_matrix = self.conjugate(skin.bind_shape_matrix);
+
+        for bind_pose in skin.bind_poses.iter_mut() {
+            *bind_pose = self.conjugate(*bind_pose);
+        }
+    }
+
+    fn animation(&self, animation: &mut Animation) {
+        let transformations = animation.sub_animations.iter_mut()
+            .flat_map(|sub_animation| sub_animation.transformations.iter_mut());
+
+        for transformation in transformations {
+            *transformation = self.conjugate(*transformation);
+        }
+    }
+
+    fn camera(&self, camera: &mut Camera) {
+        let scale = self.scale;
+
+        match &mut camera.projection {
+            Projection::Perspective { znear, zfar, .. } => {
+                *znear *= scale;
+                *zfar *= scale;
+            }
+            Projection::Orthographic { xmag, ymag, znear, zfar, .. } => {
+                *xmag = xmag.map(|xmag| xmag * scale);
+                *ymag = ymag.map(|ymag| ymag * scale);
+                *znear *= scale;
+                *zfar *= scale;
+            }
+        }
+    }
+
+    // Keeps the intensity at the same distance in the new unit
+    fn light(&self, light: &mut Light) {
+        let attenuation = match &mut light.light_type {
+            LightType::Point(attenuation) | LightType::Spot { attenuation, .. } => attenuation,
+            _ => return,
+        };
+
+        attenuation.linear /= self.scale;
+        attenuation.quadratic /= self.scale * self.scale;
+    }
+}
+
+impl Document {
+    // Rewrites the document into the given axis convention and unit. Converts geometry,
+    // node transforms, skins, animations, cameras and lights, then updates the asset
+    pub fn normalize(&mut self, up_axis: UpAxis, unit: Unit) {
+        let conversion = Conversion::new(&self.asset, up_axis, unit.meter);
+
+        for mesh in self.geometries.values_mut() {
+            conversion.mesh(mesh);
+        }
+
+        let scene_nodes = self.scenes.iter_mut().flat_map(|scene| scene.nodes.iter_mut());
+        for node in scene_nodes.chain(self.library_nodes.values_mut()) {
+            conversion.node(node);
+        }
+
+        for skin in self.skins.values_mut() {
+            conversion.skin(skin);
+        }
+
+        for animation in self.animations.values_mut() {
+            conversion.animation(animation);
+        }
+
+        for camera in self.cameras.values_mut() {
+            conversion.camera(camera);
+        }
+
+        for light in self.lights.values_mut() {
+            conversion.light(light);
+        }
+
+        self.asset.up_axis = up_axis;
+        self.asset.unit = unit;
+    }
+}
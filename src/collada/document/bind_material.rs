@@ -0,0 +1,109 @@
+use collada::{error::*, document::{effect::Texture, material::Material}};
+use xml_tree::*;
+use std::error::Error;
+use std::collections::HashMap;
+
+// Connects a texture coordinate semantic used by an effect to an input of the mesh
+#[derive(Clone, Debug)]
+pub struct BindVertexInput {
+    // Semantic used by the effect textures, such as UVMap
+    pub semantic: String,
+    // Semantic of the mesh input, usually TEXCOORD
+    pub input_semantic: String,
+    pub input_set: Option<usize>,
+}
+
+impl BindVertexInput {
+    pub fn parse_bind_vertex_input(node: &XmlNode) -> Result<BindVertexInput, Box<dyn Error>> {
+        let semantic = node.get_attribute_with_name("semantic").ok_or(MissingAttributeError { attribute_name: "semantic".to_string() })?;
+        let input_semantic = node.get_attribute_with_name("input_semantic").ok_or(MissingAttributeError { attribute_name: "input_semantic".to_string() })?;
+        let input_set = match node.get_attribute_with_name("input_set") {
+            Some(set) => Some(set.parse()?),
+            None => None,
+        };
+
+        Ok(BindVertexInput {
+            semantic: semantic.to_string(),
+            input_semantic: input_semantic.to_string(),
+            input_set,
+        })
+    }
+}
+
+// Material used for the primitives with a material symbol in one instance
+#[derive(Clone, Debug)]
+pub struct InstanceMaterial {
+    pub symbol: String,
+    // Id of the material
+    pub target: String,
+    pub vertex_inputs: Vec<BindVertexInput>,
+}
+
+impl InstanceMaterial {
+    pub fn parse_instance_material(node: &XmlNode, tree: &XmlTree) -> Result<InstanceMaterial, Box<dyn Error>> {
+        let symbol = node.get_attribute_with_name("symbol").ok_or(MissingAttributeError { attribute_name: "symbol".to_string() })?;
+        let target = node.get_attribute_with_name("target").ok_or(MissingAttributeError { attribute_name: "target".to_string() })?;
+
+        let mut vertex_inputs = vec![];
+        for child in node.get_children_with_name("bind_vertex_input", tree) {
+            vertex_inputs.push(BindVertexInput::parse_bind_vertex_input(child)?);
+        }
+
+        Ok(InstanceMaterial {
+            symbol: symbol.to_string(),
+            target: target.trim_start_matches('#').to_string(),
+            vertex_inputs,
+        })
+    }
+
+    // Texture coordinate set of the mesh read by a texture, None when the instance does not bind it
+    pub fn tex_coord_set(&self, tex_coord: &str) -> Option<usize> {
+        self.vertex_inputs.iter()
+            .find(|input| input.semantic == tex_coord && input.input_semantic == "TEXCOORD")
+            .map(|input| input.input_set.unwrap_or(0))
+    }
+}
+
+// Material bindings of a geometry or controller instance
+#[derive(Clone, Debug, Default)]
+pub struct BindMaterial {
+    pub materials: Vec<InstanceMaterial>,
+}
+
+impl BindMaterial {
+    pub fn instance_material(&self, symbol: &str) -> Option<&InstanceMaterial> {
+        self.materials.iter().find(|material| material.symbol == symbol)
+    }
+
+    // Finds the material bound to a primitive material symbol
+    pub fn resolve<'a>(&'a self, symbol: &str, materials: &'a HashMap<String, Material>) -> Option<BoundMaterial<'a>> {
+        let instance = self.instance_material(symbol)?;
+        let material = materials.get(&instance.target)?;
+
+        Some(BoundMaterial { material, instance })
+    }
+
+    pub fn parse_bind_material(node: &XmlNode, tree: &XmlTree) -> Result<BindMaterial, Box<dyn Error>> {
+        let mut materials = vec![];
+
+        for technique in node.get_children_with_name("technique_common", tree) {
+            for child in technique.get_children_with_name("instance_material", tree) {
+                materials.push(InstanceMaterial::parse_instance_material(child, tree)?);
+            }
+        }
+
+        Ok(BindMaterial { materials })
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct BoundMaterial<'a> {
+    pub material: &'a Material,
+    pub instance: &'a InstanceMaterial,
+}
+
+impl<'a> BoundMaterial<'a> {
+    pub fn tex_coord_set(&self, texture: &Texture) -> Option<usize> {
+        self.instance.tex_coord_set(&texture.tex_coord)
+    }
+}
@@ -0,0 +1,133 @@
+use collada::error::*;
+use xml_tree::*;
+use std::error::Error;
+
+// Field of view values are in degrees, as written in the document. Any two of the
+// field of view or magnification values and the aspect ratio can be given
+#[derive(Copy, Clone, Debug)]
+pub enum Projection {
+    Perspective {
+        xfov: Option<f32>,
+        yfov: Option<f32>,
+        aspect_ratio: Option<f32>,
+        znear: f32,
+        zfar: f32,
+    },
+    Orthographic {
+        xmag: Option<f32>,
+        ymag: Option<f32>,
+        aspect_ratio: Option<f32>,
+        znear: f32,
+        zfar: f32,
+    },
+}
+
+impl Projection {
+    pub fn znear(&self) -> f32 {
+        match *self {
+            Projection::Perspective { znear, .. } | Projection::Orthographic { znear, .. } => znear,
+        }
+    }
+
+    pub fn zfar(&self) -> f32 {
+        match *self {
+            Projection::Perspective { zfar, .. } | Projection::Orthographic { zfar, .. } => zfar,
+        }
+    }
+
+    // Width divided by height, computed from the horizontal and vertical values when not given
+    pub fn aspect_ratio(&self) -> Option<f32> {
+        match *self {
+            Projection::Perspective { aspect_ratio: Some(aspect_ratio), .. } => Some(aspect_ratio),
+            Projection::Perspective { xfov: Some(xfov), yfov: Some(yfov), .. } => {
+                Some((xfov.to_radians() / 2.0).tan() / (yfov.to_radians() / 2.0).tan())
+            }
+            Projection::Orthographic { aspect_ratio: Some(aspect_ratio), .. } => Some(aspect_ratio),
+            Projection::Orthographic { xmag: Some(xmag), ymag: Some(ymag), .. } => Some(xmag / ymag),
+            _ => None,
+        }
+    }
+
+    // Vertical field of view in degrees, computed from the horizontal one when not given
+    pub fn yfov(&self) -> Option<f32> {
+        match *self {
+            Projection::Perspective { yfov: Some(yfov), .. } => Some(yfov),
+            Projection::Perspective { xfov: Some(xfov), aspect_ratio: Some(aspect_ratio), .. } => {
+                Some((2.0 * ((xfov.to_radians() / 2.0).tan() / aspect_ratio).atan()).to_degrees())
+            }
+            _ => None,
+        }
+    }
+
+    // Vertical magnification, computed from the horizontal one when not given
+    pub fn ymag(&self) -> Option<f32> {
+        match *self {
+            Projection::Orthographic { ymag: Some(ymag), .. } => Some(ymag),
+            Projection::Orthographic { xmag: Some(xmag), aspect_ratio: Some(aspect_ratio), .. } => Some(xmag / aspect_ratio),
+            _ => None,
+        }
+    }
+
+    pub fn parse_projection(node: &XmlNode, tree: &XmlTree) -> Result<Projection, Box<dyn Error>> {
+        let mut values: [Option<f32>; 5] = [None; 5];
+        let names = match node.name.local_name.as_ref() {
+            "perspective" => ["xfov", "yfov", "aspect_ratio", "znear", "zfar"],
+            "orthographic" => ["xmag", "ymag", "aspect_ratio", "znear", "zfar"],
+            _ => return Err(Box::new(CameraParseError)),
+        };
+
+        for child in tree.nodes_iter(node.get_children()) {
+            let child = child.unwrap();
+
+            if let Some(i) = names.iter().position(|name| *name == child.name.local_name) {
+                let characters = child.get_characters().ok_or(CameraParseError)?;
+                values[i] = Some(characters.trim().parse()?);
+            }
+        }
+
+        let znear = values[3].ok_or(CameraParseError)?;
+        let zfar = values[4].ok_or(CameraParseError)?;
+
+        match node.name.local_name.as_ref() {
+            "perspective" => Ok(Projection::Perspective { xfov: values[0], yfov: values[1], aspect_ratio: values[2], znear, zfar }),
+            _ => Ok(Projection::Orthographic { xmag: values[0], ymag: values[1], aspect_ratio: values[2], znear, zfar }),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Camera {
+    pub id: String,
+    pub name: Option<String>,
+    pub projection: Projection,
+}
+
+impl Camera {
+    pub fn parse_camera(node: &XmlNode, tree: &XmlTree) -> Result<Camera, Box<dyn Error>> {
+        if node.name.local_name != "camera" {
+            return Err(Box::new(CameraParseError));
+        }
+        let id = node.get_attribute_with_name("id").ok_or(MissingAttributeError { attribute_name: "id".to_string() })?;
+        let name = node.get_attribute_with_name("name").map(|name| name.to_string());
+        let mut projection = None;
+
+        for optics in node.get_children_with_name("optics", tree) {
+            for technique in optics.get_children_with_name("technique_common", tree) {
+                for child in tree.nodes_iter(technique.get_children()) {
+                    let child = child.unwrap();
+
+                    match projection.is_none() {
+                        true => projection = Some(Projection::parse_projection(child, tree)?),
+                        false => return Err(Box::new(CameraParseError)),
+                    }
+                }
+            }
+        }
+
+        Ok(Camera {
+            id: id.to_string(),
+            name,
+            projection: projection.ok_or(CameraParseError)?,
+        })
+    }
+}
@@ -0,0 +1,375 @@
+use collada::{util::*, error::*};
+use xml_tree::*;
+use math::Vector4;
+use std::error::Error;
+use std::collections::HashMap;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum ShadingModel {
+    Constant,
+    Lambert,
+    Phong,
+    Blinn,
+}
+
+impl ShadingModel {
+    pub fn from_name(name: &str) -> Option<ShadingModel> {
+        match name {
+            "constant" => Some(ShadingModel::Constant),
+            "lambert" => Some(ShadingModel::Lambert),
+            "phong" => Some(ShadingModel::Phong),
+            "blinn" => Some(ShadingModel::Blinn),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Texture {
+    // Sid of the sampler parameter, some exporters use the image id directly
+    pub texture: String,
+    // Semantic of the texture coordinates, bound to a set by the material instance
+    pub tex_coord: String,
+    // Id of the image found by following the sampler and surface parameters
+    pub image: Option<String>,
+}
+
+#[derive(Clone, Debug)]
+pub enum ColorOrTexture {
+    // Red, green, blue and alpha
+    Color(Vector4),
+    Texture(Texture),
+}
+
+impl ColorOrTexture {
+    pub fn color(&self) -> Option<Vector4> {
+        match self {
+            ColorOrTexture::Color(color) => Some(*color),
+            ColorOrTexture::Texture(_) => None,
+        }
+    }
+
+    pub fn texture(&self) -> Option<&Texture> {
+        match self {
+            ColorOrTexture::Color(_) => None,
+            ColorOrTexture::Texture(texture) => Some(texture),
+        }
+    }
+
+    // None when the value references a parameter that is not a color of the effect
+    fn parse(node: &XmlNode, tree: &XmlTree, parameters: &HashMap<String, EffectParameter>) -> Result<Option<ColorOrTexture>, Box<dyn Error>> {
+        for child in tree.nodes_iter(node.get_children()) {
+            let child = child.unwrap();
+
+            match child.name.local_name.as_ref() {
+                "color" => return Ok(Some(ColorOrTexture::Color(parse_color(child)?))),
+                "param" => {
+                    let reference = child.get_attribute_with_name("ref").ok_or(MissingAttributeError { attribute_name: "ref".to_string() })?;
+                    return Ok(match parameters.get(reference) {
+                        Some(EffectParameter::Color(color)) => Some(ColorOrTexture::Color(*color)),
+                        _ => None,
+                    });
+                }
+                "texture" => {
+                    let texture = child.get_attribute_with_name("texture").ok_or(MissingAttributeError { attribute_name: "texture".to_string() })?;
+                    let tex_coord = child.get_attribute_with_name("texcoord").ok_or(MissingAttributeError { attribute_name: "texcoord".to_string() })?;
+
+                    return Ok(Some(ColorOrTexture::Texture(Texture {
+                        texture: texture.to_string(),
+                        tex_coord: tex_coord.to_string(),
+                        image: None,
+                    })));
+                }
+                _ => {}
+            }
+        }
+
+        Err(Box::new(EffectParseError))
+    }
+}
+
+// How the transparent color is turned into opacity
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Opaque {
+    // Opacity is the alpha channel
+    AOne,
+    // Opacity is one minus the alpha channel
+    AZero,
+    // Opacity is one minus the luminance of the color
+    RgbZero,
+    // Opacity is the luminance of the color
+    RgbOne,
+}
+
+impl Opaque {
+    pub fn from_name(name: &str) -> Option<Opaque> {
+        match name {
+            "A_ONE" => Some(Opaque::AOne),
+            "A_ZERO" => Some(Opaque::AZero),
+            "RGB_ZERO" => Some(Opaque::RgbZero),
+            "RGB_ONE" => Some(Opaque::RgbOne),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Transparent {
+    pub opaque: Opaque,
+    pub value: ColorOrTexture,
+}
+
+// Lighting parameters of a profile_COMMON technique, values missing from the file are None
+#[derive(Clone, Debug)]
+pub struct Technique {
+    pub shading: ShadingModel,
+    pub emission: Option<ColorOrTexture>,
+    pub ambient: Option<ColorOrTexture>,
+    pub diffuse: Option<ColorOrTexture>,
+    pub specular: Option<ColorOrTexture>,
+    pub shininess: Option<f32>,
+    pub reflective: Option<ColorOrTexture>,
+    pub reflectivity: Option<f32>,
+    pub transparent: Option<Transparent>,
+    pub transparency: Option<f32>,
+    pub index_of_refraction: Option<f32>,
+}
+
+impl Technique {
+    pub fn new(shading: ShadingModel) -> Technique {
+        Technique {
+            shading,
+            emission: None,
+            ambient: None,
+            diffuse: None,
+            specular: None,
+            shininess: None,
+            reflective: None,
+            reflectivity: None,
+            transparent: None,
+            transparency: None,
+            index_of_refraction: None,
+        }
+    }
+
+    pub fn textures(&self) -> impl Iterator<Item = &Texture> {
+        let transparent = self.transparent.as_ref().map(|transparent| &transparent.value);
+        let values = vec![&self.emission, &self.ambient, &self.diffuse, &self.specular, &self.reflective];
+
+        values.into_iter()
+            .filter_map(|value| value.as_ref())
+            .chain(transparent)
+            .filter_map(|value| value.texture())
+    }
+
+    fn textures_mut(&mut self) -> impl Iterator<Item = &mut Texture> {
+        let transparent = self.transparent.as_mut().map(|transparent| &mut transparent.value);
+        let values = vec![&mut self.emission, &mut self.ambient, &mut self.diffuse, &mut self.specular, &mut self.reflective];
+
+        values.into_iter()
+            .filter_map(|value| value.as_mut())
+            .chain(transparent)
+            .filter_map(|value| match value {
+                ColorOrTexture::Texture(texture) => Some(texture),
+                ColorOrTexture::Color(_) => None,
+            })
+    }
+
+    // Values given as <param ref> are read from the parameters of the effect
+    pub fn parse_technique(node: &XmlNode, tree: &XmlTree, parameters: &HashMap<String, EffectParameter>) -> Result<Technique, Box<dyn Error>> {
+        let shading = ShadingModel::from_name(&node.name.local_name).ok_or(EffectParseError)?;
+        let mut technique = Technique::new(shading);
+
+        for child in tree.nodes_iter(node.get_children()) {
+            let child = child.unwrap();
+
+            match child.name.local_name.as_ref() {
+                "emission" => technique.emission = ColorOrTexture::parse(child, tree, parameters)?,
+                "ambient" => technique.ambient = ColorOrTexture::parse(child, tree, parameters)?,
+                "diffuse" => technique.diffuse = ColorOrTexture::parse(child, tree, parameters)?,
+                "specular" => technique.specular = ColorOrTexture::parse(child, tree, parameters)?,
+                "shininess" => technique.shininess = parse_float(child, tree, parameters)?,
+                "reflective" => technique.reflective = ColorOrTexture::parse(child, tree, parameters)?,
+                "reflectivity" => technique.reflectivity = parse_float(child, tree, parameters)?,
+                "transparent" => {
+                    let opaque = match child.get_attribute_with_name("opaque") {
+                        Some(opaque) => Opaque::from_name(opaque).ok_or(EffectParseError)?,
+                        None => Opaque::AOne,
+                    };
+                    let value = ColorOrTexture::parse(child, tree, parameters)?;
+                    technique.transparent = value.map(|value| Transparent { opaque, value });
+                }
+                "transparency" => technique.transparency = parse_float(child, tree, parameters)?,
+                "index_of_refraction" => technique.index_of_refraction = parse_float(child, tree, parameters)?,
+                _ => {}
+            }
+        }
+
+        Ok(technique)
+    }
+}
+
+fn parse_color(node: &XmlNode) -> Result<Vector4, Box<dyn Error>> {
+    let characters = node.get_characters().ok_or(EffectParseError)?;
+    let array: Vec<f32> = parse_array(characters)?;
+
+    match array.len() {
+        3 => Ok(Vector4 { x: array[0], y: array[1], z: array[2], w: 1.0 }),
+        4 => Ok(Vector4 { x: array[0], y: array[1], z: array[2], w: array[3] }),
+        _ => Err(Box::new(EffectParseError)),
+    }
+}
+
+// None when the value references a parameter that is not a float of the effect
+fn parse_float(node: &XmlNode, tree: &XmlTree, parameters: &HashMap<String, EffectParameter>) -> Result<Option<f32>, Box<dyn Error>> {
+    for child in tree.nodes_iter(node.get_children()) {
+        let child = child.unwrap();
+
+        match child.name.local_name.as_ref() {
+            "float" => {
+                let characters = child.get_characters().ok_or(EffectParseError)?;
+                return Ok(Some(characters.trim().parse()?));
+            }
+            "param" => {
+                let reference = child.get_attribute_with_name("ref").ok_or(MissingAttributeError { attribute_name: "ref".to_string() })?;
+                return Ok(match parameters.get(reference) {
+                    Some(EffectParameter::Float(value)) => Some(*value),
+                    _ => None,
+                });
+            }
+            _ => {}
+        }
+    }
+
+    Err(Box::new(EffectParseError))
+}
+
+// Value of a newparam, only the parameters needed to find texture images and the values
+// that techniques can reference are kept
+#[derive(Clone, Debug)]
+pub enum EffectParameter {
+    Float(f32),
+    // Value of a float3 or float4 parameter, float3 has an alpha of one
+    Color(Vector4),
+    // Surface created from an image
+    Surface { image: String },
+    // Sampler reading a surface parameter, or an image directly in COLLADA 1.5
+    Sampler2D { surface: Option<String>, image: Option<String> },
+}
+
+impl EffectParameter {
+    pub fn parse_parameter(node: &XmlNode, tree: &XmlTree) -> Result<Option<EffectParameter>, Box<dyn Error>> {
+        for child in tree.nodes_iter(node.get_children()) {
+            let child = child.unwrap();
+
+            match child.name.local_name.as_ref() {
+                "float" => {
+                    let characters = child.get_characters().ok_or(EffectParseError)?;
+                    return Ok(Some(EffectParameter::Float(characters.trim().parse()?)));
+                }
+                "float3" | "float4" => return Ok(Some(EffectParameter::Color(parse_color(child)?))),
+                "surface" => {
+                    let init_from = child.get_children_with_name("init_from", tree).next().ok_or(EffectParseError)?;
+                    let image = init_from.get_characters().ok_or(EffectParseError)?;
+                    return Ok(Some(EffectParameter::Surface { image: image.trim().to_string() }));
+                }
+                "sampler2D" => {
+                    let surface = child.get_children_with_name("source", tree).next()
+                        .and_then(|source| source.get_characters())
+                        .map(|source| source.trim().to_string());
+                    let image = child.get_children_with_name("instance_image", tree).next()
+                        .and_then(|instance| instance.get_attribute_with_name("url"))
+                        .map(|url| url.trim_start_matches('#').to_string());
+                    return Ok(Some(EffectParameter::Sampler2D { surface, image }));
+                }
+                _ => {}
+            }
+        }
+
+        Ok(None)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Effect {
+    pub id: String,
+    pub name: Option<String>,
+    // Parameters of the effect and its profile by sid
+    pub parameters: HashMap<String, EffectParameter>,
+    pub technique: Technique,
+}
+
+impl Effect {
+    // Follows a texture through its sampler and surface parameters to the id of the image
+    pub fn image_of_texture<'a>(&'a self, texture: &'a str) -> Option<&'a str> {
+        match self.parameters.get(texture) {
+            Some(EffectParameter::Sampler2D { image: Some(image), .. }) => Some(image),
+            Some(EffectParameter::Sampler2D { surface: Some(surface), .. }) => match self.parameters.get(surface) {
+                Some(EffectParameter::Surface { image }) => Some(image),
+                _ => None,
+            }
+            Some(_) => None,
+            // Texture names the image without any parameters
+            None => Some(texture),
+        }
+    }
+
+    pub fn parse_effect(node: &XmlNode, tree: &XmlTree) -> Result<Effect, Box<dyn Error>> {
+        if node.name.local_name != "effect" {
+            return Err(Box::new(EffectParseError));
+        }
+        let id = node.get_attribute_with_name("id").ok_or(MissingAttributeError { attribute_name: "id".to_string() })?;
+        let name = node.get_attribute_with_name("name").map(|name| name.to_string());
+
+        let mut parameters = HashMap::new();
+        let mut technique = None;
+
+        parse_parameters(node, tree, &mut parameters)?;
+
+        for profile in node.get_children_with_name("profile_COMMON", tree) {
+            parse_parameters(profile, tree, &mut parameters)?;
+
+            for child in profile.get_children_with_name("technique", tree) {
+                for shading in tree.nodes_iter(child.get_children()) {
+                    let shading = shading.unwrap();
+                    if ShadingModel::from_name(&shading.name.local_name).is_none() {
+                        continue;
+                    }
+
+                    match technique.is_none() {
+                        true => technique = Some(Technique::parse_technique(shading, tree, &parameters)?),
+                        false => return Err(Box::new(EffectParseError)),
+                    }
+                }
+            }
+        }
+
+        let mut effect = Effect {
+            id: id.to_string(),
+            name,
+            parameters,
+            technique: technique.ok_or(EffectParseError)?,
+        };
+
+        let mut images = vec![];
+        for texture in effect.technique.textures() {
+            images.push(effect.image_of_texture(&texture.texture).map(|image| image.to_string()));
+        }
+        for (texture, image) in effect.technique.textures_mut().zip(images) {
+            texture.image = image;
+        }
+
+        Ok(effect)
+    }
+}
+
+fn parse_parameters(node: &XmlNode, tree: &XmlTree, parameters: &mut HashMap<String, EffectParameter>) -> Result<(), Box<dyn Error>> {
+    for child in node.get_children_with_name("newparam", tree) {
+        let sid = child.get_attribute_with_name("sid").ok_or(MissingAttributeError { attribute_name: "sid".to_string() })?;
+        if let Some(parameter) = EffectParameter::parse_parameter(child, tree)? {
+            parameters.insert(sid.to_string(), parameter);
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,188 @@
+use collada::error::*;
+use xml_tree::*;
+use std::error::Error;
+use std::str;
+use std::path::{Path, PathBuf};
+
+#[derive(Clone, Debug)]
+pub enum ImageSource {
+    // File referenced by the image. The uri is kept as written in the document,
+    // the path is decoded and resolved against the directory of the document when known
+    File { uri: String, path: PathBuf },
+    // Image stored in the document as hex, format is the file format such as PNG if given
+    Embedded { format: Option<String>, data: Vec<u8> },
+}
+
+#[derive(Clone, Debug)]
+pub struct Image {
+    pub id: String,
+    pub name: Option<String>,
+    pub source: ImageSource,
+}
+
+impl Image {
+    pub fn path(&self) -> Option<&Path> {
+        match &self.source {
+            ImageSource::File { path, .. } => Some(path),
+            ImageSource::Embedded { .. } => None,
+        }
+    }
+
+    pub fn data(&self) -> Option<&[u8]> {
+        match &self.source {
+            ImageSource::File { .. } => None,
+            ImageSource::Embedded { data, .. } => Some(data),
+        }
+    }
+
+    // Joins relative file paths onto the directory of the document
+    pub fn resolve_path(&mut self, directory: &Path) {
+        if let ImageSource::File { path, .. } = &mut self.source {
+            if !is_absolute(path) {
+                *path = directory.join(&path);
+            }
+        }
+    }
+
+    pub fn parse_image(node: &XmlNode, tree: &XmlTree) -> Result<Image, Box<dyn Error>> {
+        if node.name.local_name != "image" {
+            return Err(Box::new(ImageParseError));
+        }
+        let id = node.get_attribute_with_name("id").ok_or(MissingAttributeError { attribute_name: "id".to_string() })?;
+        let name = node.get_attribute_with_name("name").map(|name| name.to_string());
+        let mut source = None;
+
+        for child in tree.nodes_iter(node.get_children()) {
+            let child = child.unwrap();
+
+            let image_source = match child.name.local_name.as_ref() {
+                "init_from" => parse_init_from(child, tree)?,
+                // Hex data of COLLADA 1.4 images
+                "data" => {
+                    let characters = child.get_characters().ok_or(ImageParseError)?;
+                    let format = node.get_attribute_with_name("format").map(|format| format.to_string());
+                    ImageSource::Embedded { format, data: parse_hex(characters)? }
+                }
+                _ => continue,
+            };
+
+            match source.is_none() {
+                true => source = Some(image_source),
+                false => return Err(Box::new(ImageParseError)),
+            }
+        }
+
+        Ok(Image {
+            id: id.to_string(),
+            name,
+            source: source.ok_or(ImageParseError)?,
+        })
+    }
+}
+
+// COLLADA 1.4 has the uri as the characters of init_from, 1.5 puts it in a ref child
+// or embeds the image in a hex child
+fn parse_init_from(node: &XmlNode, tree: &XmlTree) -> Result<ImageSource, Box<dyn Error>> {
+    if let Some(uri) = node.get_characters() {
+        return Ok(file_source(uri));
+    }
+
+    for child in tree.nodes_iter(node.get_children()) {
+        let child = child.unwrap();
+
+        match child.name.local_name.as_ref() {
+            "ref" => return Ok(file_source(child.get_characters().ok_or(ImageParseError)?)),
+            "hex" => {
+                let characters = child.get_characters().ok_or(ImageParseError)?;
+                let format = child.get_attribute_with_name("format").map(|format| format.to_string());
+                return Ok(ImageSource::Embedded { format, data: parse_hex(characters)? });
+            }
+            _ => {}
+        }
+    }
+
+    Err(Box::new(ImageParseError))
+}
+
+fn file_source(uri: &str) -> ImageSource {
+    let uri = uri.trim();
+
+    ImageSource::File {
+        uri: uri.to_string(),
+        path: uri_to_path(uri),
+    }
+}
+
+// Turns a relative reference, plain path or file uri into a path, decoding percent escapes
+fn uri_to_path(uri: &str) -> PathBuf {
+    let path = match uri.get(..5) {
+        Some(scheme) if scheme.eq_ignore_ascii_case("file:") => {
+            let path = &uri[5..];
+            // Drop the empty authority of file:///path, keep the host of file://host/path as a network path
+            match path.starts_with("///") {
+                true => &path[2..],
+                false => path,
+            }
+        }
+        _ => uri,
+    };
+
+    let path = percent_decode(path);
+
+    // Windows drive paths are written as /C:/path in uris
+    let bytes = path.as_bytes();
+    if bytes.len() >= 3 && bytes[0] == b'/' && bytes[1].is_ascii_alphabetic() && bytes[2] == b':' {
+        return PathBuf::from(&path[1..]);
+    }
+
+    PathBuf::from(path)
+}
+
+fn percent_decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+
+    let mut i = 0;
+    while i < bytes.len() {
+        let escape = bytes.get(i + 1..i + 3)
+            .and_then(|hex| str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+
+        match (bytes[i], escape) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            (byte, _) => {
+                decoded.push(byte);
+                i += 1;
+            }
+        }
+    }
+
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+// Paths with a drive letter are absolute even when the document is read on another platform
+fn is_absolute(path: &Path) -> bool {
+    let text = path.to_string_lossy();
+    let bytes = text.as_bytes();
+    let drive = bytes.len() >= 2 && bytes[0].is_ascii_alphabetic() && bytes[1] == b':';
+
+    path.is_absolute() || path.has_root() || drive || text.starts_with("\\\\")
+}
+
+fn parse_hex(characters: &str) -> Result<Vec<u8>, ImageParseError> {
+    let digits: Vec<u8> = characters.bytes().filter(|byte| !byte.is_ascii_whitespace()).collect();
+    if !digits.len().is_multiple_of(2) {
+        return Err(ImageParseError);
+    }
+
+    let mut data = Vec::with_capacity(digits.len() / 2);
+    for pair in digits.chunks(2) {
+        let pair = str::from_utf8(pair).map_err(|_| ImageParseError)?;
+        data.push(u8::from_str_radix(pair, 16).map_err(|_| ImageParseError)?);
+    }
+
+    Ok(data)
+}
@@ -0,0 +1,166 @@
+use collada::{util::*, error::*};
+use xml_tree::*;
+use math::Vector3;
+use std::error::Error;
+use std::collections::HashMap;
+
+#[derive(Copy, Clone, Debug)]
+pub struct Attenuation {
+    pub constant: f32,
+    pub linear: f32,
+    pub quadratic: f32,
+}
+
+impl Attenuation {
+    // Intensity factor at the given distance from the light
+    pub fn factor(&self, distance: f32) -> f32 {
+        1.0 / (self.constant + self.linear * distance + self.quadratic * distance * distance)
+    }
+}
+
+impl Default for Attenuation {
+    fn default() -> Attenuation {
+        Attenuation {
+            constant: 1.0,
+            linear: 0.0,
+            quadratic: 0.0,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+pub enum LightType {
+    Ambient,
+    // Points down the negative z axis of its node
+    Directional,
+    Point(Attenuation),
+    // Points down the negative z axis of its node, the falloff angle is in degrees
+    Spot {
+        attenuation: Attenuation,
+        falloff_angle: f32,
+        falloff_exponent: f32,
+    },
+}
+
+// Values of the light in the blender profile of its extra data by name, such as energy,
+// spotsize and spotblend. Only values that are numbers are kept
+#[derive(Clone, Debug, Default)]
+pub struct BlenderLight {
+    pub values: HashMap<String, f32>,
+}
+
+impl BlenderLight {
+    pub fn value(&self, name: &str) -> Option<f32> {
+        self.values.get(name).cloned()
+    }
+
+    pub fn energy(&self) -> Option<f32> {
+        self.value("energy")
+    }
+
+    pub fn parse_blender_light(node: &XmlNode, tree: &XmlTree) -> BlenderLight {
+        let mut values = HashMap::new();
+
+        for child in tree.nodes_iter(node.get_children()) {
+            let child = child.unwrap();
+            let value = child.get_characters().and_then(|characters| characters.trim().parse().ok());
+
+            if let Some(value) = value {
+                values.insert(child.name.local_name.clone(), value);
+            }
+        }
+
+        BlenderLight { values }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Light {
+    pub id: String,
+    pub name: Option<String>,
+    pub color: Vector3,
+    pub light_type: LightType,
+    pub blender: Option<BlenderLight>,
+}
+
+fn parse_float(node: &XmlNode, tree: &XmlTree, name: &str) -> Result<Option<f32>, Box<dyn Error>> {
+    match node.get_children_with_name(name, tree).next() {
+        Some(child) => {
+            let characters = child.get_characters().ok_or(LightParseError)?;
+            Ok(Some(characters.trim().parse()?))
+        }
+        None => Ok(None),
+    }
+}
+
+fn parse_attenuation(node: &XmlNode, tree: &XmlTree) -> Result<Attenuation, Box<dyn Error>> {
+    let default = Attenuation::default();
+
+    Ok(Attenuation {
+        constant: parse_float(node, tree, "constant_attenuation")?.unwrap_or(default.constant),
+        linear: parse_float(node, tree, "linear_attenuation")?.unwrap_or(default.linear),
+        quadratic: parse_float(node, tree, "quadratic_attenuation")?.unwrap_or(default.quadratic),
+    })
+}
+
+impl Light {
+    pub fn parse_light(node: &XmlNode, tree: &XmlTree) -> Result<Light, Box<dyn Error>> {
+        if node.name.local_name != "light" {
+            return Err(Box::new(LightParseError));
+        }
+        let id = node.get_attribute_with_name("id").ok_or(MissingAttributeError { attribute_name: "id".to_string() })?;
+        let name = node.get_attribute_with_name("name").map(|name| name.to_string());
+        let mut light = None;
+        let mut blender = None;
+
+        for child in tree.nodes_iter(node.get_children()) {
+            let child = child.unwrap();
+
+            match child.name.local_name.as_ref() {
+                "technique_common" => for technique in tree.nodes_iter(child.get_children()) {
+                    let technique = technique.unwrap();
+
+                    let light_type = match technique.name.local_name.as_ref() {
+                        "ambient" => LightType::Ambient,
+                        "directional" => LightType::Directional,
+                        "point" => LightType::Point(parse_attenuation(technique, tree)?),
+                        "spot" => LightType::Spot {
+                            attenuation: parse_attenuation(technique, tree)?,
+                            falloff_angle: parse_float(technique, tree, "falloff_angle")?.unwrap_or(180.0),
+                            falloff_exponent: parse_float(technique, tree, "falloff_exponent")?.unwrap_or(0.0),
+                        },
+                        _ => continue,
+                    };
+
+                    let color = technique.get_children_with_name("color", tree).next().ok_or(LightParseError)?;
+                    let color: Vec<f32> = parse_array(color.get_characters().ok_or(LightParseError)?)?;
+                    if color.len() != 3 {
+                        return Err(Box::new(LightParseError));
+                    }
+                    let color = Vector3 { x: color[0], y: color[1], z: color[2] };
+
+                    match light.is_none() {
+                        true => light = Some((color, light_type)),
+                        false => return Err(Box::new(LightParseError)),
+                    }
+                }
+                "extra" => for technique in child.get_children_with_name("technique", tree) {
+                    if technique.get_attribute_with_name("profile") == Some("blender") {
+                        blender = Some(BlenderLight::parse_blender_light(technique, tree));
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        let (color, light_type) = light.ok_or(LightParseError)?;
+
+        Ok(Light {
+            id: id.to_string(),
+            name,
+            color,
+            light_type,
+            blender,
+        })
+    }
+}
@@ -0,0 +1,48 @@
+use collada::{error::*, document::effect::*};
+use xml_tree::*;
+use std::error::Error;
+use std::collections::HashMap;
+
+// Material with the technique of its effect
+#[derive(Clone, Debug)]
+pub struct Material {
+    pub id: String,
+    pub name: Option<String>,
+    // Id of the instanced effect
+    pub effect: String,
+    pub technique: Technique,
+}
+
+impl Material {
+    pub fn shading(&self) -> ShadingModel {
+        self.technique.shading
+    }
+
+    // None when the material has no instance_effect or its effect was not loaded,
+    // either because it is missing or unsupported
+    pub fn parse_material(node: &XmlNode, tree: &XmlTree, effects: &HashMap<String, Effect>) -> Result<Option<Material>, Box<dyn Error>> {
+        if node.name.local_name != "material" {
+            return Err(Box::new(MaterialParseError));
+        }
+        let id = node.get_attribute_with_name("id").ok_or(MissingAttributeError { attribute_name: "id".to_string() })?;
+        let name = node.get_attribute_with_name("name").map(|name| name.to_string());
+
+        let instance = match node.get_children_with_name("instance_effect", tree).next() {
+            Some(instance) => instance,
+            None => return Ok(None),
+        };
+        let url = instance.get_attribute_with_name("url").ok_or(MissingAttributeError { attribute_name: "url".to_string() })?;
+        let effect = url.trim_start_matches('#');
+        let technique = match effects.get(effect) {
+            Some(effect) => effect.technique.clone(),
+            None => return Ok(None),
+        };
+
+        Ok(Some(Material {
+            id: id.to_string(),
+            name,
+            effect: effect.to_string(),
+            technique,
+        }))
+    }
+}
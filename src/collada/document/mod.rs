@@ -2,11 +2,27 @@
 pub mod geometry;
 pub mod controller;
 pub mod visual_scene;
+pub mod effect;
+pub mod material;
+pub mod image;
+pub mod bind_material;
+pub mod camera;
+pub mod light;
+pub mod scene;
+pub mod asset;
 
 pub use self::geometry::*;
 pub use self::controller::*;
 pub use self::visual_scene::*;
-use collada::{Animation, Skin, Skeleton, Mesh};
+pub use self::effect::*;
+pub use self::material::*;
+pub use self::image::*;
+pub use self::bind_material::*;
+pub use self::camera::*;
+pub use self::light::*;
+pub use self::scene::*;
+pub use self::asset::*;
+use collada::{Animation, Skin, Skeleton, Mesh, error::*};
 use std::{
     error::Error,
     fs::File,
@@ -24,6 +40,22 @@ pub struct Document {
     pub animations: HashMap<String, Animation>,
     pub skins: HashMap<String, Skin>,
     pub scenes: Vec<VisualScene>,
+    pub effects: HashMap<String, Effect>,
+    // Ids of effects without a profile_COMMON technique, they and their materials are not loaded
+    pub unsupported_effects: Vec<String>,
+    pub materials: HashMap<String, Material>,
+    // Ids of materials that are not loaded because they have no instance_effect,
+    // or their effect is missing or unsupported
+    pub skipped_materials: Vec<String>,
+    pub images: HashMap<String, Image>,
+    pub cameras: HashMap<String, Camera>,
+    pub lights: HashMap<String, Light>,
+    // Nodes of library_nodes by id, copied into the scenes where they are instanced
+    pub library_nodes: HashMap<String, Node>,
+    // Urls of instance_node references dropped because the node would end up inside itself
+    pub cyclic_instances: Vec<String>,
+    pub scene: Option<Scene>,
+    pub asset: Asset,
 }
 
 impl Document {
@@ -33,6 +65,17 @@ impl Document {
             animations: HashMap::new(),
             skins: HashMap::new(),
             scenes: vec![],
+            effects: HashMap::new(),
+            unsupported_effects: vec![],
+            materials: HashMap::new(),
+            skipped_materials: vec![],
+            images: HashMap::new(),
+            cameras: HashMap::new(),
+            lights: HashMap::new(),
+            library_nodes: HashMap::new(),
+            cyclic_instances: vec![],
+            scene: None,
+            asset: Asset::default(),
         }
     }
 
@@ -56,6 +99,51 @@ impl Document {
         scene.get_skeletons()
     }
 
+    pub fn material_with_id<'a>(&'a self, id: &str) -> Option<&'a Material> {
+        self.materials.get(id)
+    }
+
+    pub fn image_with_id<'a>(&'a self, id: &str) -> Option<&'a Image> {
+        self.images.get(id)
+    }
+
+    // Image of a texture used by a material
+    pub fn texture_image<'a>(&'a self, texture: &Texture) -> Option<&'a Image> {
+        let image = texture.image.as_ref()?;
+        self.images.get(image)
+    }
+
+    // Material bound to a primitive material symbol by an instance
+    pub fn bound_material<'a>(&'a self, bind_material: &'a BindMaterial, symbol: &str) -> Option<BoundMaterial<'a>> {
+        bind_material.resolve(symbol, &self.materials)
+    }
+
+    pub fn camera_with_id<'a>(&'a self, id: &str) -> Option<&'a Camera> {
+        self.cameras.get(id)
+    }
+
+    pub fn light_with_id<'a>(&'a self, id: &str) -> Option<&'a Light> {
+        self.lights.get(id)
+    }
+
+    pub fn scene_with_id<'a>(&'a self, id: &str) -> Option<&'a VisualScene> {
+        self.scenes.iter().find(|scene| scene.id == id)
+    }
+
+    // Visual scene instanced by the scene element. Documents without a scene
+    // element have no active scene, the first visual scene is used instead
+    pub fn active_scene(&self) -> Option<&VisualScene> {
+        match &self.scene {
+            Some(Scene { visual_scene: Some(id), .. }) => self.scene_with_id(id),
+            Some(_) => None,
+            None => self.scenes.first(),
+        }
+    }
+
+    pub fn active_scene_skeletons(&self) -> Vec<Skeleton> {
+        self.active_scene().map(|scene| scene.get_skeletons()).unwrap_or_default()
+    }
+
     pub fn get_skeletons(&self) -> Vec<Skeleton> {
         let mut skeletons = vec![];
         
@@ -128,6 +216,179 @@ impl Document {
         Ok(())
     }
 
+    pub fn parse_library_nodes(&mut self, tree: &XmlTree) -> Result<(), Box<dyn Error>> {
+        for node in tree.nodes_with_name("library_nodes") {
+            for child in tree.nodes_iter(node.get_children()) {
+                let child = child.unwrap();
+
+                if child.name.local_name != "node" {
+                    continue;
+                }
+                let node = Node::parse_node(child, tree)?;
+                let id = node.id.clone().ok_or(MissingAttributeError { attribute_name: "id".to_string() })?;
+                self.library_nodes.insert(id, node);
+            }
+        }
+
+        Ok(())
+    }
+
+    // Expands instance_node references in the node library and the scenes. Nodes can be instanced
+    // from the node library or from any visual scene, at any depth
+    pub fn expand_instance_nodes(&mut self) {
+        let mut nodes = HashMap::new();
+        for node in self.library_nodes.values() {
+            node.add_nodes_by_id(&mut nodes);
+        }
+        for scene in self.scenes.iter() {
+            for node in scene.nodes.iter() {
+                node.add_nodes_by_id(&mut nodes);
+            }
+        }
+
+        let mut expander = NodeExpander::new(&nodes);
+
+        // Sorted so the same instances are dropped every time when nodes instance each other
+        let mut ids: Vec<String> = self.library_nodes.keys().cloned().collect();
+        ids.sort();
+        for id in ids.iter() {
+            if let Some(node) = self.library_nodes.get_mut(id) {
+                expander.expand(node);
+            }
+        }
+
+        for scene in self.scenes.iter_mut() {
+            for node in scene.nodes.iter_mut() {
+                expander.expand(node);
+            }
+        }
+
+        self.cyclic_instances = expander.cyclic;
+    }
+
+    // Only the asset of the document itself, elements can have assets of their own
+    pub fn parse_asset(&mut self, tree: &XmlTree) -> Result<(), Box<dyn Error>> {
+        for root in tree.nodes_with_name("COLLADA") {
+            if let Some(node) = root.get_children_with_name("asset", tree).next() {
+                self.asset = Asset::parse_asset(node, tree)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn parse_scene(&mut self, tree: &XmlTree) -> Result<(), Box<dyn Error>> {
+        for node in tree.nodes_with_name("scene") {
+            match self.scene.is_none() {
+                true => self.scene = Some(Scene::parse_scene(node, tree)?),
+                false => return Err(Box::new(SceneParseError)),
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn parse_effects(&mut self, tree: &XmlTree) -> Result<(), Box<dyn Error>> {
+        for node in tree.nodes_with_name("library_effects") {
+            for child in tree.nodes_iter(node.get_children()) {
+                let child = child.unwrap();
+
+                if child.name.local_name != "effect" {
+                    continue;
+                }
+                // Effects written only for other profiles such as GLSL or CG are skipped
+                if child.get_children_with_name("profile_COMMON", tree).next().is_none() {
+                    let id = child.get_attribute_with_name("id").ok_or(MissingAttributeError { attribute_name: "id".to_string() })?;
+                    self.unsupported_effects.push(id.to_string());
+                    continue;
+                }
+                let effect = Effect::parse_effect(child, tree)?;
+                self.effects.insert(effect.id.clone(), effect);
+            }
+        }
+
+        Ok(())
+    }
+
+    // Effects need to be parsed first, materials copy the technique of their effect.
+    // Materials whose effect can not be found are skipped
+    pub fn parse_materials(&mut self, tree: &XmlTree) -> Result<(), Box<dyn Error>> {
+        for node in tree.nodes_with_name("library_materials") {
+            for child in tree.nodes_iter(node.get_children()) {
+                let child = child.unwrap();
+
+                if child.name.local_name != "material" {
+                    continue;
+                }
+                match Material::parse_material(child, tree, &self.effects)? {
+                    Some(material) => {
+                        self.materials.insert(material.id.clone(), material);
+                    }
+                    None => if let Some(id) = child.get_attribute_with_name("id") {
+                        self.skipped_materials.push(id.to_string());
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn parse_images(&mut self, tree: &XmlTree) -> Result<(), Box<dyn Error>> {
+        for node in tree.nodes_with_name("library_images") {
+            for child in tree.nodes_iter(node.get_children()) {
+                let child = child.unwrap();
+
+                if child.name.local_name != "image" {
+                    continue;
+                }
+                let image = Image::parse_image(child, tree)?;
+                self.images.insert(image.id.clone(), image);
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn parse_cameras(&mut self, tree: &XmlTree) -> Result<(), Box<dyn Error>> {
+        for node in tree.nodes_with_name("library_cameras") {
+            for child in tree.nodes_iter(node.get_children()) {
+                let child = child.unwrap();
+
+                if child.name.local_name != "camera" {
+                    continue;
+                }
+                let camera = Camera::parse_camera(child, tree)?;
+                self.cameras.insert(camera.id.clone(), camera);
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn parse_lights(&mut self, tree: &XmlTree) -> Result<(), Box<dyn Error>> {
+        for node in tree.nodes_with_name("library_lights") {
+            for child in tree.nodes_iter(node.get_children()) {
+                let child = child.unwrap();
+
+                if child.name.local_name != "light" {
+                    continue;
+                }
+                let light = Light::parse_light(child, tree)?;
+                self.lights.insert(light.id.clone(), light);
+            }
+        }
+
+        Ok(())
+    }
+
+    // Makes relative image paths relative to the given directory instead of the working directory
+    pub fn resolve_image_paths(&mut self, directory: &Path) {
+        for image in self.images.values_mut() {
+            image.resolve_path(directory);
+        }
+    }
+
     pub fn mesh_iter(&self) -> impl Iterator<Item = (&String, &Mesh)> {
         self.geometries.iter()
     }
@@ -146,21 +407,37 @@ impl Document {
     pub fn parse_document(tree: &XmlTree) -> Result<Document, Box<dyn Error>> {
         let mut document = Document::new();
 
+        document.parse_asset(tree)?;
         document.parse_geometries(tree)?;
         document.parse_animations(tree)?;
         document.parse_skins(tree)?;
+        document.parse_library_nodes(tree)?;
         document.parse_visual_scenes(tree)?;
+        document.expand_instance_nodes();
+        document.parse_scene(tree)?;
+        document.parse_effects(tree)?;
+        document.parse_materials(tree)?;
+        document.parse_images(tree)?;
+        document.parse_cameras(tree)?;
+        document.parse_lights(tree)?;
 
         Ok(document)
     }
 
+    // Image paths are resolved against the directory of the file
     pub fn parse_from_file(path: impl AsRef<Path>) -> Result<Document, Box<dyn Error>> {
-        let file = File::open(path).unwrap();
+        let file = File::open(path.as_ref()).unwrap();
         let file = BufReader::new(file);
 
         let parser = EventReader::new(file);
         let tree = XmlTree::parse_xml(parser)?;
-        Ok(Document::parse_document(&tree)?)
+        let mut document = Document::parse_document(&tree)?;
+
+        if let Some(directory) = path.as_ref().parent() {
+            document.resolve_image_paths(directory);
+        }
+
+        Ok(document)
     }
 
     pub fn print_document(&self) {
@@ -179,6 +456,11 @@ impl Document {
             println!("\n{:?}", skin);
         }
 
+        println!("\nMaterials");
+        for material in &self.materials {
+            println!("\n{:?}", material);
+        }
+
         println!("\nScenes");
         for scene in &self.scenes {
             println!("\n{:?}", scene);
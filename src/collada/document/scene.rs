@@ -0,0 +1,39 @@
+use collada::error::*;
+use xml_tree::*;
+use std::error::Error;
+
+// Scenes instanced by the scene element, urls without the leading #
+#[derive(Clone, Debug, Default)]
+pub struct Scene {
+    pub visual_scene: Option<String>,
+    pub physics_scenes: Vec<String>,
+    pub kinematics_scenes: Vec<String>,
+}
+
+impl Scene {
+    pub fn parse_scene(node: &XmlNode, tree: &XmlTree) -> Result<Scene, Box<dyn Error>> {
+        if node.name.local_name != "scene" {
+            return Err(Box::new(SceneParseError));
+        }
+        let mut scene = Scene::default();
+
+        for child in tree.nodes_iter(node.get_children()) {
+            let child = child.unwrap();
+            let url = || child.get_attribute_with_name("url")
+                .map(|url| url.trim_start_matches('#').to_string())
+                .ok_or(MissingAttributeError { attribute_name: "url".to_string() });
+
+            match child.name.local_name.as_ref() {
+                "instance_visual_scene" => match scene.visual_scene.is_none() {
+                    true => scene.visual_scene = Some(url()?),
+                    false => return Err(Box::new(SceneParseError)),
+                }
+                "instance_physics_scene" => scene.physics_scenes.push(url()?),
+                "instance_kinematics_scene" => scene.kinematics_scenes.push(url()?),
+                _ => {}
+            }
+        }
+
+        Ok(scene)
+    }
+}
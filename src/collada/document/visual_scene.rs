@@ -1,12 +1,14 @@
-use crate::collada::{util::*, Skeleton, error::*};
+use crate::collada::{Skeleton, error::*, transform::*, document::bind_material::BindMaterial};
 use xml_tree::*;
 use std::error::Error;
 use math::Matrix4;
+use std::collections::HashMap;
 
 #[derive(Clone, Debug)]
 pub struct InstanceController {
     pub url: String,
     pub skeleton: String,
+    pub bind_material: BindMaterial,
 }
 
 impl InstanceController {
@@ -14,6 +16,7 @@ impl InstanceController {
 
         let mut found_skeleton = false;
         let mut skeleton = String::new();
+        let mut bind_material = None;
         let url = node.get_attribute_with_name("url").ok_or(MissingAttributeError { attribute_name: "url".to_string() })?;
 
         for child in tree.nodes_iter(node.get_children()) {
@@ -27,6 +30,10 @@ impl InstanceController {
                     },
                     true => return Err(Box::new(ControllerParseError)),
                 }
+                "bind_material" => match bind_material.is_none() {
+                    true => bind_material = Some(BindMaterial::parse_bind_material(child, tree)?),
+                    false => return Err(Box::new(ControllerParseError)),
+                }
                 _ => {}
             }
         }
@@ -38,6 +45,36 @@ impl InstanceController {
         Ok(InstanceController {
             url: url.to_string(),
             skeleton,
+            bind_material: bind_material.unwrap_or_default(),
+        })
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct InstanceGeometry {
+    pub url: String,
+    pub bind_material: BindMaterial,
+}
+
+impl InstanceGeometry {
+    pub fn geometry_id(&self) -> &str {
+        self.url.trim_start_matches('#')
+    }
+
+    pub fn parse_geometry(node: &XmlNode, tree: &XmlTree) -> Result<InstanceGeometry, Box<dyn Error>> {
+        let url = node.get_attribute_with_name("url").ok_or(MissingAttributeError { attribute_name: "url".to_string() })?;
+        let mut bind_material = None;
+
+        for child in node.get_children_with_name("bind_material", tree) {
+            match bind_material.is_none() {
+                true => bind_material = Some(BindMaterial::parse_bind_material(child, tree)?),
+                false => return Err(Box::new(VisualSceneError)),
+            }
+        }
+
+        Ok(InstanceGeometry {
+            url: url.to_string(),
+            bind_material: bind_material.unwrap_or_default(),
         })
     }
 }
@@ -75,6 +112,47 @@ impl VisualScene {
         controllers
     }
 
+    pub fn get_geometries(&self) -> Vec<InstanceGeometry> {
+        let mut geometries = vec![];
+
+        for node in self.nodes.iter() {
+            node.add_geometry(&mut geometries);
+        }
+
+        geometries
+    }
+
+    // Every instance in the scene with the nodes from the root of the scene down to the node holding it.
+    // The world transform of an instance is the product of the matrices of the nodes
+    pub fn instances_with_path(&self) -> Vec<(Vec<&Node>, &Instance)> {
+        let mut instances = vec![];
+        let mut path = vec![];
+
+        for node in self.nodes.iter() {
+            node.add_instances_with_path(&mut path, &mut instances);
+        }
+
+        instances
+    }
+
+    pub fn camera_instances(&self) -> Vec<(Vec<&Node>, &str)> {
+        self.instances_with_path().into_iter()
+            .filter_map(|(path, instance)| match instance {
+                Instance::Camera { url } => Some((path, url.trim_start_matches('#'))),
+                _ => None,
+            })
+            .collect()
+    }
+
+    pub fn light_instances(&self) -> Vec<(Vec<&Node>, &str)> {
+        self.instances_with_path().into_iter()
+            .filter_map(|(path, instance)| match instance {
+                Instance::Light { url } => Some((path, url.trim_start_matches('#'))),
+                _ => None,
+            })
+            .collect()
+    }
+
     pub fn parse_scene(node: &XmlNode, tree: &XmlTree) -> Result<VisualScene, Box<dyn Error>> {
         if node.name.local_name != "visual_scene" {
             return Err(Box::new(VisualSceneError));
@@ -101,96 +179,192 @@ impl VisualScene {
     }
 }
 
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum NodeType {
+    Node,
+    Joint,
+}
+
+// Object placed at a node, the url references the instanced element in its library
 #[derive(Clone, Debug)]
-pub enum NodeData {
-    Multi { 
-        matrix: Matrix4, 
-        sub_nodes: Vec<Node> 
-    },
-    ObjectInstance {
-        matrix: Matrix4,
-        controller: InstanceController,
-    },
-    Skeleton(Skeleton),
-    Other,
+pub enum Instance {
+    Geometry(InstanceGeometry),
+    Controller(InstanceController),
+    Camera { url: String },
+    Light { url: String },
+    Node { url: String },
+}
+
+impl Instance {
+    pub fn url(&self) -> &str {
+        match self {
+            Instance::Geometry(geometry) => &geometry.url,
+            Instance::Controller(controller) => &controller.url,
+            Instance::Camera { url } | Instance::Light { url } | Instance::Node { url } => url,
+        }
+    }
+
+    fn parse_url(node: &XmlNode) -> Result<String, MissingAttributeError> {
+        let url = node.get_attribute_with_name("url").ok_or(MissingAttributeError { attribute_name: "url".to_string() })?;
+        Ok(url.to_string())
+    }
+
+    pub fn parse_instance(node: &XmlNode, tree: &XmlTree) -> Result<Option<Instance>, Box<dyn Error>> {
+        let instance = match node.name.local_name.as_ref() {
+            "instance_geometry" => Instance::Geometry(InstanceGeometry::parse_geometry(node, tree)?),
+            "instance_controller" => Instance::Controller(InstanceController::parse_controller(node, tree)?),
+            "instance_camera" => Instance::Camera { url: Instance::parse_url(node)? },
+            "instance_light" => Instance::Light { url: Instance::parse_url(node)? },
+            "instance_node" => Instance::Node { url: Instance::parse_url(node)? },
+            _ => return Ok(None),
+        };
+
+        Ok(Some(instance))
+    }
 }
 
 #[derive(Clone, Debug)]
 pub struct Node {
-    pub name: String,
-    pub id: String,
-    pub data: NodeData,
+    pub id: Option<String>,
+    pub name: Option<String>,
+    pub sid: Option<String>,
+    pub node_type: NodeType,
+    // Transform elements in document order, kept for animations that target them by sid
+    pub transforms: Vec<Transform>,
+    // Transform relative to the parent node, the product of the transform elements
+    pub matrix: Matrix4,
+    pub instances: Vec<Instance>,
+    pub children: Vec<Node>,
 }
 
 impl Node {
+    pub fn is_joint(&self) -> bool {
+        self.node_type == NodeType::Joint
+    }
+
+    pub fn transform_with_sid(&self, sid: &str) -> Option<&Transform> {
+        self.transforms.iter().find(|transform| transform.sid.as_ref().map(|other| &other[..]) == Some(sid))
+    }
+
     pub fn parse_node(node: &XmlNode, tree: &XmlTree) -> Result<Node, Box<dyn Error>> {
-        let id = node.get_attribute_with_name("id")
-            .ok_or(MissingAttributeError { attribute_name: "id".to_string() })?;
-        let name = node.get_attribute_with_name("name")
-            .ok_or(MissingAttributeError { attribute_name: "name".to_string() })?;
-        let _type = node.get_attribute_with_name("type")
-            .ok_or(MissingAttributeError { attribute_name: "type".to_string() })?;
-
-        if _type == "JOINT" {
-            let skeleton = Skeleton::parse_skeleton(node, tree)?;
-            return Ok(Node {
-                name: name.to_string(),
-                id: id.to_string(),
-                data: NodeData::Skeleton(skeleton)
-            });
-        }
-
-        let matrix = parse_transformation(node, tree)?;
-
-        if let Some(controller) = node.get_children_with_name("instance_controller", tree).next() {
-            let controller = InstanceController::parse_controller(controller, tree)?;
-            return Ok(Node {
-                name: name.to_string(),
-                id: id.to_string(),
-                data: NodeData::ObjectInstance { matrix, controller }
-            });
-        }
-
-        let mut sub_nodes = vec![];
-        for child in tree.nodes_iter(node.get_children()).map(|child| child.unwrap()) {
-            if &child.name.local_name == "node" {
-                let node = Node::parse_node(child, tree)?;
-                sub_nodes.push(node);
+        if node.name.local_name != "node" {
+            let id = node.get_attribute_with_name("id").map(|id| id.to_string());
+            return Err(Box::new(SceneNodeError { id }));
+        }
+
+        let id = node.get_attribute_with_name("id").map(|id| id.to_string());
+        let name = node.get_attribute_with_name("name").map(|name| name.to_string());
+        let sid = node.get_attribute_with_name("sid").map(|sid| sid.to_string());
+        let node_type = match node.get_attribute_with_name("type") {
+            Some("JOINT") => NodeType::Joint,
+            _ => NodeType::Node,
+        };
+
+        // Joints become skeleton nodes which are found by id and sid
+        if node_type == NodeType::Joint {
+            if id.is_none() {
+                return Err(Box::new(MissingAttributeError { attribute_name: "id".to_string() }));
+            }
+            if sid.is_none() {
+                return Err(Box::new(MissingAttributeError { attribute_name: "sid".to_string() }));
             }
         }
 
-        if sub_nodes.is_empty() {
-            return Ok(Node {
-                name: name.to_string(),
-                id: id.to_string(),
-                data: NodeData::Other
-            });
+        let transforms = parse_transforms(node, tree)?;
+        let matrix = compose(&transforms);
+        let mut instances = vec![];
+        let mut children = vec![];
+
+        for child in tree.nodes_iter(node.get_children()) {
+            let child = child.unwrap();
+
+            if child.name.local_name == "node" {
+                children.push(Node::parse_node(child, tree)?);
+            }
+            else if let Some(instance) = Instance::parse_instance(child, tree)? {
+                instances.push(instance);
+            }
         }
 
         Ok(Node {
-            name: name.to_string(),
-            id: id.to_string(),
-            data: NodeData::Multi { matrix, sub_nodes }
+            id,
+            name,
+            sid,
+            node_type,
+            transforms,
+            matrix,
+            instances,
+            children,
         })
     }
 
+    // Every joint without a joint parent is the root of a skeleton
     pub fn add_skeletons(&self, skeletons: &mut Vec<Skeleton>) {
-        match &self.data {
-            NodeData::Multi { sub_nodes, .. } => for node in sub_nodes.iter() {
-                node.add_skeletons(skeletons)
-            },
-            NodeData::Skeleton(skeleton) => skeletons.push(skeleton.clone()),
-            _ => ()
+        if self.is_joint() {
+            if let Ok(skeleton) = Skeleton::from_node(self) {
+                skeletons.push(skeleton);
+            }
+            return;
+        }
+
+        for node in self.children.iter() {
+            node.add_skeletons(skeletons);
         }
     }
 
     pub fn add_controller(&self, controllers: &mut Vec<InstanceController>) {
-        match &self.data {
-            NodeData::Multi { sub_nodes, .. } => for node in sub_nodes.iter() {
-                node.add_controller(controllers);
+        for instance in self.instances.iter() {
+            if let Instance::Controller(controller) = instance {
+                controllers.push(controller.clone());
             }
-            NodeData::ObjectInstance { controller, .. } => controllers.push(controller.clone()),
-            _ => ()
+        }
+
+        for node in self.children.iter() {
+            node.add_controller(controllers);
+        }
+    }
+
+    pub fn add_geometry(&self, geometries: &mut Vec<InstanceGeometry>) {
+        for instance in self.instances.iter() {
+            if let Instance::Geometry(geometry) = instance {
+                geometries.push(geometry.clone());
+            }
+        }
+
+        for node in self.children.iter() {
+            node.add_geometry(geometries);
+        }
+    }
+
+    pub fn add_instances_with_path<'a>(&'a self, path: &mut Vec<&'a Node>, instances: &mut Vec<(Vec<&'a Node>, &'a Instance)>) {
+        path.push(self);
+
+        for instance in self.instances.iter() {
+            instances.push((path.clone(), instance));
+        }
+
+        for node in self.children.iter() {
+            node.add_instances_with_path(path, instances);
+        }
+
+        path.pop();
+    }
+
+    // Adds the node and every node below it that has an id
+    pub fn add_nodes_by_id(&self, nodes: &mut HashMap<String, Node>) {
+        if let Some(id) = &self.id {
+            nodes.insert(id.clone(), self.clone());
+        }
+
+        for node in self.children.iter() {
+            node.add_nodes_by_id(nodes);
+        }
+    }
+
+    fn clear_ids(&mut self) {
+        self.id = None;
+        for node in self.children.iter_mut() {
+            node.clear_ids();
         }
     }
 
@@ -200,4 +374,78 @@ impl Node {
 
         skeletons
     }
-}
\ No newline at end of file
+}
+
+// Replaces instance_node references with copies of the instanced nodes added as children.
+// Every instanced node is expanded once and copied from then on, without ids so ids stay unique.
+// Instances that would make a node contain itself are dropped and listed in cyclic,
+// instances of nodes in other documents or missing from this one are kept as they are
+pub struct NodeExpander<'a> {
+    nodes: &'a HashMap<String, Node>,
+    expanded: HashMap<String, Node>,
+    // Ids of the nodes being expanded
+    stack: Vec<String>,
+    pub cyclic: Vec<String>,
+}
+
+impl<'a> NodeExpander<'a> {
+    pub fn new(nodes: &'a HashMap<String, Node>) -> NodeExpander<'a> {
+        NodeExpander {
+            nodes,
+            expanded: HashMap::new(),
+            stack: vec![],
+            cyclic: vec![],
+        }
+    }
+
+    pub fn expand(&mut self, node: &mut Node) {
+        if let Some(id) = &node.id {
+            self.stack.push(id.clone());
+        }
+
+        for child in node.children.iter_mut() {
+            self.expand(child);
+        }
+
+        for instance in ::std::mem::take(&mut node.instances) {
+            let id = match &instance {
+                Instance::Node { url } if url.starts_with('#') && self.nodes.contains_key(&url[1..]) => url[1..].to_string(),
+                _ => {
+                    node.instances.push(instance);
+                    continue;
+                }
+            };
+
+            match self.expanded_copy(&id) {
+                Some(copy) => node.children.push(copy),
+                None => {
+                    let url = format!("#{}", id);
+                    if !self.cyclic.contains(&url) {
+                        self.cyclic.push(url);
+                    }
+                }
+            }
+        }
+
+        if node.id.is_some() {
+            self.stack.pop();
+        }
+    }
+
+    // None when the node is being expanded, so it would end up inside itself
+    fn expanded_copy(&mut self, id: &str) -> Option<Node> {
+        if self.stack.iter().any(|current| current == id) {
+            return None;
+        }
+
+        if !self.expanded.contains_key(id) {
+            let mut node = self.nodes.get(id)?.clone();
+            self.expand(&mut node);
+            self.expanded.insert(id.to_string(), node);
+        }
+
+        let mut copy = self.expanded.get(id)?.clone();
+        copy.clear_ids();
+        Some(copy)
+    }
+}
@@ -111,6 +111,17 @@ impl Display for MeshError {
 
 impl Error for MeshError {}
 
+#[derive(Copy, Clone, Debug)]
+pub struct BufferError;
+
+impl Display for BufferError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "Failed to build mesh buffers")
+    }
+}
+
+impl Error for BufferError {}
+
 #[derive(Copy, Clone, Debug)]
 pub struct VertexWeightsError;
 
@@ -236,4 +247,79 @@ impl Display for SceneNodeError {
 
 impl Error for SceneNodeError {}
 
+#[derive(Copy, Clone, Debug)]
+pub struct EffectParseError;
+
+impl Display for EffectParseError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "Failed to parse effect")
+    }
+}
+
+impl Error for EffectParseError {}
+
+#[derive(Copy, Clone, Debug)]
+pub struct MaterialParseError;
+
+impl Display for MaterialParseError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "Failed to parse material")
+    }
+}
+
+impl Error for MaterialParseError {}
+
+#[derive(Copy, Clone, Debug)]
+pub struct ImageParseError;
+
+impl Display for ImageParseError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "Failed to parse image")
+    }
+}
+
+impl Error for ImageParseError {}
+
+#[derive(Copy, Clone, Debug)]
+pub struct CameraParseError;
+
+impl Display for CameraParseError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "Failed to parse camera")
+    }
+}
+
+impl Error for CameraParseError {}
+
+#[derive(Copy, Clone, Debug)]
+pub struct LightParseError;
+
+impl Display for LightParseError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "Failed to parse light")
+    }
+}
+
+impl Error for LightParseError {}
+
+#[derive(Copy, Clone, Debug)]
+pub struct SceneParseError;
+
+impl Display for SceneParseError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "Failed to parse scene")
+    }
+}
+
+impl Error for SceneParseError {}
+
+#[derive(Copy, Clone, Debug)]
+pub struct AssetParseError;
+
+impl Display for AssetParseError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "Failed to parse asset")
+    }
+}
 
+impl Error for AssetParseError {}
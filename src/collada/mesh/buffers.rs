@@ -0,0 +1,335 @@
+use collada::{GenericMesh, Shape, PTNCIndex, error::*};
+use super::{Vertex, Position};
+use std::collections::HashMap;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum VertexAttribute {
+    Position,
+    // Texture coordinates with the given set number
+    TexCoord(usize),
+    Normal,
+    // Colors with the given set number
+    Color(usize),
+    // Tangents and binormals of the given texture coordinate set
+    Tangent(usize),
+    Binormal(usize),
+}
+
+impl VertexAttribute {
+    pub fn components(&self) -> usize {
+        match self {
+            VertexAttribute::TexCoord(_) => 2,
+            _ => 3,
+        }
+    }
+}
+
+// Order of the attributes in each interleaved vertex
+#[derive(Clone, Debug)]
+pub struct VertexLayout {
+    attributes: Vec<VertexAttribute>,
+}
+
+impl VertexLayout {
+    pub fn new() -> VertexLayout {
+        VertexLayout {
+            attributes: vec![],
+        }
+    }
+
+    pub fn with(mut self, attribute: VertexAttribute) -> VertexLayout {
+        self.attributes.push(attribute);
+        self
+    }
+
+    pub fn attributes(&self) -> &[VertexAttribute] {
+        self.attributes.as_slice()
+    }
+
+    // Number of floats in each vertex
+    pub fn stride(&self) -> usize {
+        self.attributes.iter().map(|attribute| attribute.components()).sum()
+    }
+
+    // Offset in floats of the attribute from the start of each vertex
+    pub fn offset_of(&self, attribute: VertexAttribute) -> Option<usize> {
+        let mut offset = 0;
+        for current in self.attributes.iter() {
+            if *current == attribute {
+                return Some(offset);
+            }
+            offset += current.components();
+        }
+
+        None
+    }
+}
+
+impl Default for VertexLayout {
+    fn default() -> VertexLayout {
+        VertexLayout::new()
+            .with(VertexAttribute::Position)
+            .with(VertexAttribute::TexCoord(0))
+            .with(VertexAttribute::Normal)
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum IndexFormat {
+    U16,
+    U32,
+    // U16 when every vertex can be addressed with it, otherwise U32
+    Smallest,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Topology {
+    Triangles,
+    Lines,
+}
+
+#[derive(Clone, Debug)]
+pub enum IndexBuffer {
+    U16(Vec<u16>),
+    U32(Vec<u32>),
+}
+
+impl IndexBuffer {
+    pub fn len(&self) -> usize {
+        match self {
+            IndexBuffer::U16(indices) => indices.len(),
+            IndexBuffer::U32(indices) => indices.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+// Range of the index buffer drawn with one material
+#[derive(Clone, Debug)]
+pub struct DrawRange {
+    // Material symbol of the primitives in the range
+    pub material: Option<String>,
+    pub start: usize,
+    pub count: usize,
+}
+
+#[derive(Clone, Debug)]
+pub struct MeshBuffers {
+    pub layout: VertexLayout,
+    pub topology: Topology,
+    pub vertices: Vec<f32>,
+    pub indices: IndexBuffer,
+    // Indices are ordered by material so every material has a single range
+    pub ranges: Vec<DrawRange>,
+}
+
+impl MeshBuffers {
+    pub fn vertex_count(&self) -> usize {
+        match self.layout.stride() {
+            0 => 0,
+            stride => self.vertices.len() / stride,
+        }
+    }
+}
+
+// Converts a mesh into a single interleaved vertex buffer and an index buffer,
+// sharing vertices whose indices match for every attribute in the layout
+#[derive(Clone, Debug)]
+pub struct BufferBuilder {
+    layout: VertexLayout,
+    index_format: IndexFormat,
+    topology: Topology,
+}
+
+impl BufferBuilder {
+    pub fn new() -> BufferBuilder {
+        BufferBuilder {
+            layout: VertexLayout::default(),
+            index_format: IndexFormat::Smallest,
+            topology: Topology::Triangles,
+        }
+    }
+
+    pub fn layout(mut self, layout: VertexLayout) -> BufferBuilder {
+        self.layout = layout;
+        self
+    }
+
+    pub fn index_format(mut self, index_format: IndexFormat) -> BufferBuilder {
+        self.index_format = index_format;
+        self
+    }
+
+    pub fn topology(mut self, topology: Topology) -> BufferBuilder {
+        self.topology = topology;
+        self
+    }
+
+    // Shapes that do not match the topology are skipped, strips and fans are expanded.
+    // Polygons need to be triangulated first
+    pub fn build<T: Vertex + Position>(&self, mesh: &GenericMesh<T>) -> Result<MeshBuffers, BufferError> {
+        let mut unique: HashMap<PTNCIndex, u32> = HashMap::new();
+        let mut corners: Vec<PTNCIndex> = vec![];
+        let mut indices: Vec<u32> = vec![];
+        let mut ranges: Vec<DrawRange> = vec![];
+
+        {
+            let mut add_corner = |corner: PTNCIndex, indices: &mut Vec<u32>| {
+                let corner = self.used_indices(mesh, corner);
+                let next = corners.len() as u32;
+                let index = *unique.entry(corner.clone()).or_insert(next);
+                if index == next {
+                    corners.push(corner);
+                }
+                indices.push(index);
+            };
+
+            if mesh.groups.is_empty() {
+                for shape in mesh.shapes.iter() {
+                    self.add_shape(shape, &mut indices, &mut add_corner)?;
+                }
+                ranges.push(DrawRange { material: None, start: 0, count: indices.len() });
+            }
+
+            for material in mesh.materials() {
+                let start = indices.len();
+                for shape in mesh.shapes_with_material(material) {
+                    self.add_shape(shape, &mut indices, &mut add_corner)?;
+                }
+                ranges.push(DrawRange {
+                    material: material.map(|material| material.to_string()),
+                    start,
+                    count: indices.len() - start,
+                });
+            }
+        }
+
+        let mut vertices = Vec::with_capacity(corners.len() * self.layout.stride());
+        for corner in corners.iter() {
+            for attribute in self.layout.attributes() {
+                match *attribute {
+                    VertexAttribute::Position => {
+                        let position = mesh.vertices.get(corner.position).ok_or(BufferError)?.position();
+                        vertices.extend_from_slice(&[position.x, position.y, position.z]);
+                    }
+                    VertexAttribute::TexCoord(set) => {
+                        let tex_coord = mesh.tex_coord_slot(set)
+                            .and_then(|slot| corner.tex_coords.get(slot).and_then(|i| mesh.tex_coords[slot].values.get(i)));
+                        match tex_coord {
+                            Some(tex_coord) => vertices.extend_from_slice(&[tex_coord.x, tex_coord.y]),
+                            None => vertices.extend_from_slice(&[0.0; 2]),
+                        }
+                    }
+                    VertexAttribute::Normal => match corner.normal.and_then(|i| mesh.normals.get(i)) {
+                        Some(normal) => vertices.extend_from_slice(&[normal.x, normal.y, normal.z]),
+                        None => vertices.extend_from_slice(&[0.0; 3]),
+                    }
+                    VertexAttribute::Color(set) => {
+                        let color = mesh.color_slot(set)
+                            .and_then(|slot| corner.colors.get(slot).and_then(|i| mesh.colors[slot].values.get(i)));
+                        match color {
+                            Some(color) => vertices.extend_from_slice(&[color.x, color.y, color.z]),
+                            None => vertices.extend_from_slice(&[0.0; 3]),
+                        }
+                    }
+                    VertexAttribute::Tangent(set) => {
+                        let tangent = mesh.tangent_slot(set)
+                            .and_then(|slot| corner.tangents.get(slot).and_then(|i| mesh.tangents[slot].values.get(i)));
+                        match tangent {
+                            Some(tangent) => vertices.extend_from_slice(&[tangent.x, tangent.y, tangent.z]),
+                            None => vertices.extend_from_slice(&[0.0; 3]),
+                        }
+                    }
+                    VertexAttribute::Binormal(set) => {
+                        let binormal = mesh.binormal_slot(set)
+                            .and_then(|slot| corner.binormals.get(slot).and_then(|i| mesh.binormals[slot].values.get(i)));
+                        match binormal {
+                            Some(binormal) => vertices.extend_from_slice(&[binormal.x, binormal.y, binormal.z]),
+                            None => vertices.extend_from_slice(&[0.0; 3]),
+                        }
+                    }
+                }
+            }
+        }
+
+        let fits_u16 = corners.len() <= u16::MAX as usize + 1;
+        let indices = match self.index_format {
+            IndexFormat::U16 if !fits_u16 => return Err(BufferError),
+            IndexFormat::U16 => IndexBuffer::U16(indices.iter().map(|index| *index as u16).collect()),
+            IndexFormat::Smallest if fits_u16 => IndexBuffer::U16(indices.iter().map(|index| *index as u16).collect()),
+            _ => IndexBuffer::U32(indices),
+        };
+
+        Ok(MeshBuffers {
+            layout: self.layout.clone(),
+            topology: self.topology,
+            vertices,
+            indices,
+            ranges,
+        })
+    }
+
+    fn add_shape(&self, shape: &Shape<PTNCIndex>, indices: &mut Vec<u32>, add_corner: &mut impl FnMut(PTNCIndex, &mut Vec<u32>)) -> Result<(), BufferError> {
+        let shapes = match shape {
+            Shape::Polygon(_) | Shape::PolygonWithHoles(..) => match self.topology {
+                Topology::Triangles => return Err(BufferError),
+                Topology::Lines => return Ok(()),
+            },
+            shape => shape.expand(),
+        };
+
+        for shape in shapes {
+            match (shape, self.topology) {
+                (Shape::Triangle(v0, v1, v2), Topology::Triangles) => {
+                    add_corner(v0, indices);
+                    add_corner(v1, indices);
+                    add_corner(v2, indices);
+                }
+                (Shape::Line(v0, v1), Topology::Lines) => {
+                    add_corner(v0, indices);
+                    add_corner(v1, indices);
+                }
+                _ => {}
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl BufferBuilder {
+    // Clears the indices of attributes missing from the layout so they do not split vertices
+    fn used_indices<T: Vertex>(&self, mesh: &GenericMesh<T>, corner: PTNCIndex) -> PTNCIndex {
+        let mut used = PTNCIndex::new(corner.position);
+
+        for attribute in self.layout.attributes() {
+            match *attribute {
+                VertexAttribute::Position => {}
+                VertexAttribute::TexCoord(set) => if let Some(slot) = mesh.tex_coord_slot(set) {
+                    used.tex_coords.set(slot, corner.tex_coords.get(slot));
+                }
+                VertexAttribute::Normal => used.normal = corner.normal,
+                VertexAttribute::Color(set) => if let Some(slot) = mesh.color_slot(set) {
+                    used.colors.set(slot, corner.colors.get(slot));
+                }
+                VertexAttribute::Tangent(set) => if let Some(slot) = mesh.tangent_slot(set) {
+                    used.tangents.set(slot, corner.tangents.get(slot));
+                }
+                VertexAttribute::Binormal(set) => if let Some(slot) = mesh.binormal_slot(set) {
+                    used.binormals.set(slot, corner.binormals.get(slot));
+                }
+            }
+        }
+
+        used
+    }
+}
+
+impl Default for BufferBuilder {
+    fn default() -> BufferBuilder {
+        BufferBuilder::new()
+    }
+}
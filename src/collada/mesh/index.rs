@@ -2,238 +2,183 @@ use xml_tree::*;
 use collada::{*, util::*, error::*};
 use std::error::Error;
 
-#[derive(Debug)]
-pub struct IndexAccessor {
-    vertex: Input,
-    tex_coord: Option<Input>,
-    normal: Option<Input>,
-    color: Option<Input>,
-    pub components: usize,
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Semantic {
+    Vertex,
+    Normal,
+    TexCoord,
+    Color,
+    TexTangent,
+    TexBinormal,
 }
 
-impl IndexAccessor {
-    pub fn has_tex_coord(&self) -> bool {
-        self.tex_coord.is_some()
+impl Semantic {
+    pub fn from_name(name: &str) -> Option<Semantic> {
+        match name {
+            "VERTEX" => Some(Semantic::Vertex),
+            "NORMAL" => Some(Semantic::Normal),
+            "TEXCOORD" => Some(Semantic::TexCoord),
+            "COLOR" => Some(Semantic::Color),
+            "TEXTANGENT" => Some(Semantic::TexTangent),
+            "TEXBINORMAL" => Some(Semantic::TexBinormal),
+            _ => None,
+        }
     }
+}
 
-    pub fn has_normal(&self) -> bool {
-        self.normal.is_some()
+#[derive(Debug)]
+pub struct SharedInput {
+    pub semantic: Semantic,
+    pub input: Input,
+    pub set: usize,
+    // Position of the input among the inputs with the same semantic ordered by set
+    slot: usize,
+}
+
+impl SharedInput {
+    pub fn source(&self) -> &str {
+        &self.input.source[..]
     }
-    
-    pub fn has_color(&self) -> bool {
-        self.color.is_some()
+
+    pub fn slot(&self) -> usize {
+        self.slot
     }
-    
-    pub fn ptnc_offsets(&self) -> (usize, Option<usize>, Option<usize>, Option<usize>) {
-        let normal = match &self.normal {
-            Some(input) => Some(input.offset),
-            None => None,
-        };
+}
 
-        let tex_coord = match &self.tex_coord {
-            Some(input) => Some(input.offset),
-            None => None,
-        };
+#[derive(Debug)]
+pub struct IndexAccessor {
+    inputs: Vec<SharedInput>,
+    pub components: usize,
+}
 
-        let color = match &self.color {
-            Some(input) => Some(input.offset),
-            None => None,
-        };
+impl IndexAccessor {
+    pub fn inputs(&self) -> &[SharedInput] {
+        self.inputs.as_slice()
+    }
 
-        (self.vertex.offset, tex_coord, normal, color)
+    pub fn inputs_with_semantic<'a>(&'a self, semantic: Semantic) -> impl Iterator<Item = &'a SharedInput> + 'a {
+        self.inputs.iter().filter(move |input| input.semantic == semantic)
     }
 
-    pub fn ptnc_sources(&self) -> (&str, Option<&str>, Option<&str>, Option<&str>) {
-        let normal = match &self.normal {
-            Some(input) => Some(&input.source[..]),
-            None => None,
-        };
+    fn first_with_semantic(&self, semantic: Semantic) -> Option<&SharedInput> {
+        self.inputs_with_semantic(semantic).next()
+    }
 
-        let tex_coord = match &self.tex_coord {
-            Some(input) => Some(&input.source[..]),
-            None => None,
-        };
+    pub fn has_tex_coord(&self) -> bool {
+        self.first_with_semantic(Semantic::TexCoord).is_some()
+    }
 
-        let color = match &self.color {
-            Some(input) => Some(&input.source[..]),
-            None => None,
-        };
+    pub fn has_normal(&self) -> bool {
+        self.first_with_semantic(Semantic::Normal).is_some()
+    }
 
-        (&self.vertex.source[..], tex_coord, normal, color)
+    pub fn has_color(&self) -> bool {
+        self.first_with_semantic(Semantic::Color).is_some()
     }
 
     pub fn ptn_sources(&self) -> (&str, Option<&str>, Option<&str>) {
-        let normal = match &self.normal {
-            Some(input) => Some(&input.source[..]),
-            None => None,
-        };
+        let vertex = self.first_with_semantic(Semantic::Vertex).map(|input| input.source()).unwrap_or("");
+        let tex_coord = self.first_with_semantic(Semantic::TexCoord).map(|input| input.source());
+        let normal = self.first_with_semantic(Semantic::Normal).map(|input| input.source());
 
-        let tex_coord = match &self.tex_coord {
-            Some(input) => Some(&input.source[..]),
-            None => None,
-        };
-
-        (&self.vertex.source[..], tex_coord, normal)
+        (vertex, tex_coord, normal)
     }
 
     pub fn parse_accessor(node: &XmlNode, tree: &XmlTree) -> Result<IndexAccessor, Box<dyn Error>> {
-        let mut found = false;
-        let mut vertex: (usize, Option<&str>, bool) = (0, None, false);
-        let mut normal: (Option<usize>, Option<&str>, bool)  = (None, None, false);
-        let mut color: (Option<usize>, Option<&str>, bool)  = (None, None, false);
-        let mut tex_coord: (Option<usize>, Option<&str>, bool)  = (None, None, false);
+        let mut inputs: Vec<SharedInput> = vec![];
+        // Offsets of every input, including those with semantics that are not supported
+        let mut offsets: Vec<usize> = vec![];
 
         for child in tree.nodes_iter(node.get_children()) {
             let child = child.unwrap();
-            match child.name.local_name.as_ref() {
-                "input" => {
-                    found = true;
-                    let offset = child.get_attribute_with_name("offset").ok_or(IndexAccessorError)?;
-                    let offset: usize = offset.parse()?;
-
-                    let source = match child.get_attribute_with_name("source") {
-                        Some(value) => value,
-                        None => return Err(Box::new(IndexAccessorError))
-                    };
-
-                    match child.get_attribute_with_name("semantic") {
-                        Some(value) => match value {
-                            "VERTEX" => {
-                                if vertex.2 == true {
-                                    return Err(Box::new(IndexAccessorError));
-                                }
-                                vertex = (offset, Some(source), true);
-                            }
-                            "NORMAL" => {
-                                if normal.2 == true {
-                                    return Err(Box::new(IndexAccessorError));
-                                }
-                                normal = (Some(offset), Some(source), true);
-                            }
-                            "COLOR" => {
-                                if color.2 == true {
-                                    return Err(Box::new(IndexAccessorError));
-                                }
-                                color = (Some(offset), Some(source), true);
-                            }
-                            "TEXCOORD" => {
-                                if tex_coord.2 == true {
-                                    return Err(Box::new(IndexAccessorError));
-                                }
-                                tex_coord = (Some(offset), Some(source), true);
-                            }
-                            _ => {},
-                        },
-                        _ => {},
-                    }
-                }
-                _ => {},
+            if child.name.local_name != "input" {
+                continue;
             }
-        }
 
-        let vertex_components = 1 + normal.0.is_some() as usize
-            + color.0.is_some() as usize
-            + tex_coord.0.is_some() as usize;
+            let offset = child.get_attribute_with_name("offset").ok_or(IndexAccessorError)?;
+            let offset: usize = offset.parse()?;
+            offsets.push(offset);
+            let source = child.get_attribute_with_name("source").ok_or(IndexAccessorError)?;
+            let semantic = child.get_attribute_with_name("semantic").ok_or(IndexAccessorError)?;
+            let set: usize = match child.get_attribute_with_name("set") {
+                Some(set) => set.parse()?,
+                None => 0,
+            };
+
+            let semantic = match Semantic::from_name(semantic) {
+                Some(semantic) => semantic,
+                None => continue,
+            };
+
+            // Only vertex and normal inputs are limited to a single set
+            let single = semantic == Semantic::Vertex || semantic == Semantic::Normal;
+            let duplicate = inputs.iter().any(|input| input.semantic == semantic && (single || input.set == set));
+            if duplicate {
+                return Err(Box::new(IndexAccessorError));
+            }
 
-        let vertex = Input::new(vertex.1.unwrap().to_string(), vertex.0);
+            inputs.push(SharedInput { semantic, input: Input::new(source.to_string(), offset), set, slot: 0 });
+        }
 
-        let normal = match normal.0.is_some() {
-            true => Some(Input::new(normal.1.unwrap().to_string(), normal.0.unwrap())),
-            false => None,
-        };
-        let tex_coord = match tex_coord.0.is_some() {
-            true => Some(Input::new(tex_coord.1.unwrap().to_string(), tex_coord.0.unwrap())),
-            false => None,
-        };
-        let color = match color.0.is_some() {
-            true => Some(Input::new(color.1.unwrap().to_string(), color.0.unwrap())),
-            false => None,
-        };
-        
-        let accessor = IndexAccessor {
-            vertex,
-            normal,
-            color,
-            tex_coord,
-            components: vertex_components,
+        let components = match offsets.iter().max() {
+            Some(offset) => offset + 1,
+            None => return Err(Box::new(IndexAccessorError)),
         };
 
-        if !accessor.no_duplicates() || !accessor.has_valid_offsets() || !found {
+        // Every offset up to the largest has to be used by an input
+        if !(0..components).all(|offset| offsets.contains(&offset)) {
             return Err(Box::new(IndexAccessorError));
         }
 
-        Ok(accessor)
-    }
-
-    fn no_duplicates(&self) -> bool {
-        let (vertex, tex_coord, normal, color) = self.ptnc_offsets();
-
-        !(Some(vertex) == normal 
-        || Some(vertex) == color
-        || Some(vertex) == tex_coord
-        || (normal == color && normal.is_some())
-        || (normal == tex_coord && normal.is_some())
-        || (color == tex_coord && color.is_some()))
-    }
+        let mut accessor = IndexAccessor {
+            inputs,
+            components,
+        };
 
-    fn has_valid_offsets(&self) -> bool {
-        let (vertex, tex_coord, normal, color) = self.ptnc_offsets();
+        if accessor.inputs_with_semantic(Semantic::Vertex).count() != 1 {
+            return Err(Box::new(IndexAccessorError));
+        }
 
-        for i in 0..self.components {
-            let si = Some(i);
-            if i != vertex
-            && si != normal
-            && si != tex_coord
-            && si != color {
-                return false;
-            }
+        // Sets can be numbered arbitrarily, give each input with the same semantic a slot in set order
+        let mut slots = vec![];
+        for input in accessor.inputs.iter() {
+            let slot = accessor.inputs_with_semantic(input.semantic)
+                .filter(|other| other.set < input.set)
+                .count();
+            slots.push(slot);
+        }
+        for (input, slot) in accessor.inputs.iter_mut().zip(slots) {
+            input.slot = slot;
         }
 
-        true
+        Ok(accessor)
     }
 
     pub fn get_nth_ptnc(&self, n: usize, indices: &[usize]) -> Option<PTNCIndex> {
         let start = n * self.components;
-        if n + self.components > indices.len() {
+        if start + self.components > indices.len() {
             return None;
         }
-        let (vertex, tex_coord, normal, color) = self.ptnc_offsets();
 
-        let position = indices[start + vertex];
-        let normal = match normal {
-            Some(offset) => Some(indices[start + offset]),
-            None => None
-        };
-        let tex_coord = match tex_coord {
-            Some(offset) => Some(indices[start + offset]),
-            None => None
-        };
-        let color = match color {
-            Some(offset) => Some(indices[start + offset]),
-            None => None
-        };
+        let mut index = PTNCIndex::new(0);
+        for input in self.inputs.iter() {
+            let value = indices[start + input.input.offset];
+            match input.semantic {
+                Semantic::Vertex => index.position = value,
+                Semantic::Normal => index.normal = Some(value),
+                Semantic::TexCoord => index.tex_coords.set(input.slot, Some(value)),
+                Semantic::Color => index.colors.set(input.slot, Some(value)),
+                Semantic::TexTangent => index.tangents.set(input.slot, Some(value)),
+                Semantic::TexBinormal => index.binormals.set(input.slot, Some(value)),
+            }
+        }
 
-        Some((position, tex_coord, normal, color))
+        Some(index)
     }
 
     pub fn get_nth_ptn(&self, n: usize, indices: &[usize]) -> Option<PTNIndex> {
-        let start = n * self.components;
-        if n + self.components > indices.len() {
-            return None;
-        }
-
-        let (vertex, tex_coord, normal, _) = self.ptnc_offsets();
+        let index = self.get_nth_ptnc(n, indices)?;
 
-        let position = indices[start + vertex];
-        let normal = match normal {
-            Some(offset) => Some(indices[start + offset]),
-            None => None
-        };
-        let tex_coord = match tex_coord {
-            Some(offset) => Some(indices[start + offset]),
-            None => None
-        };
-
-        Some((position, tex_coord, normal))
+        Some((index.position, index.tex_coord(), index.normal))
     }
-}
\ No newline at end of file
+}
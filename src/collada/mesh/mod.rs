@@ -1,12 +1,17 @@
 use xml_tree::*;
 use super::{*, error::*, source::DataSource};
 use self::vertices::Vertices;
+use self::index::{Semantic, SharedInput};
 use math::*;
 use std::error::Error;
 
 pub mod index;
 pub mod primitive_elements;
 pub mod vertices;
+pub mod triangulate;
+pub mod buffers;
+pub mod tangents;
+pub mod normals;
 
 pub use self::primitive_elements::*;
 
@@ -53,11 +58,7 @@ impl MeshParser {
                 "vertices" => {
                     vertices = Vertices::parse_vertices(child, tree)?;
                 }
-                "triangles" => {
-                    let primitive_element = PrimitiveElement::parse_primitive_element(child, tree)?;
-                    primitive_elements.push(primitive_element);
-                }
-                "lines" => {
+                "triangles" | "lines" | "polylist" | "polygons" | "tristrips" | "trifans" | "linestrips" => {
                     let primitive_element = PrimitiveElement::parse_primitive_element(child, tree)?;
                     primitive_elements.push(primitive_element);
                 }
@@ -84,22 +85,21 @@ impl MeshParser {
             return Err(Box::new(MeshError));
         }
 
-        let mut primitive_elements = self.primitive_elements.iter();
-        let first_primitive = primitive_elements.next().unwrap();
-
-        let sources = first_primitive.get_ptnc_sources();
-        if &sources.0[1..] != self.vertices.get_id() {
-            return Err(Box::new(MeshError));
-        }
-
         let mut shapes = vec![];
-        shapes.extend(first_primitive.ptnc_shape_iter());
-        for element in primitive_elements {
-            if sources != element.get_ptnc_sources() {
+        let mut groups = vec![];
+        for element in self.primitive_elements.iter() {
+            let vertex_input = element.get_inputs().iter().find(|input| input.semantic == Semantic::Vertex).ok_or(MeshError)?;
+            if &vertex_input.source()[1..] != self.vertices.get_id() {
                 return Err(Box::new(MeshError));
             }
 
+            let start = shapes.len();
             shapes.extend(element.ptnc_shape_iter());
+            groups.push(PrimitiveGroup {
+                material: element.material().map(|material| material.to_string()),
+                start,
+                count: shapes.len() - start,
+            });
         }
 
         let mut vertices = vec![];
@@ -110,103 +110,210 @@ impl MeshParser {
             vertices.push(vertex);
         }
 
-        let mut normals = vec![];
-        match sources.2 {
-            Some(name) => {
-                let source = self.get_source_with_name(&name[1..]).ok_or(MeshError)?;
-                let param_names = source.get_parameter_names();
-                let mut x: Option<usize> = None;
-                let mut y: Option<usize> = None; 
-                let mut z: Option<usize> = None;
-                for (i, name) in param_names.iter().enumerate() {
-                    match name.as_ref() {
-                        "X" => x = Some(i),
-                        "Y" => y = Some(i),
-                        "Z" => z = Some(i),
-                        _ => {}
-                    }
-                }
-
-                if x.is_none() || y.is_none() || z.is_none() {
-                    return Err(Box::new(MeshError));
+        // Primitive elements can use different sources or only some of the inputs, the values
+        // of every source are appended to the mesh and the indices of each element are moved
+        let mut remaps: Vec<Vec<InputRemap>> = self.primitive_elements.iter().map(|_| vec![]).collect();
+
+        let normals = self.merge_inputs(Semantic::Normal, &mut remaps, |source| {
+            self.read_source(source, &["X", "Y", "Z"], |value| Vector3 { x: value[0], y: value[1], z: value[2] })
+        })?;
+        let normals = normals.into_iter().flat_map(|normals| normals.values).collect();
+
+        let tex_coords = self.merge_inputs(Semantic::TexCoord, &mut remaps, |source| {
+            self.read_source(source, &["S", "T"], |value| Vector2 { x: value[0], y: value[1] })
+        })?;
+
+        let tangents = self.merge_inputs(Semantic::TexTangent, &mut remaps, |source| {
+            self.read_source(source, &["X", "Y", "Z"], |value| Vector3 { x: value[0], y: value[1], z: value[2] })
+        })?;
+
+        let binormals = self.merge_inputs(Semantic::TexBinormal, &mut remaps, |source| {
+            self.read_source(source, &["X", "Y", "Z"], |value| Vector3 { x: value[0], y: value[1], z: value[2] })
+        })?;
+
+        let colors = self.merge_inputs(Semantic::Color, &mut remaps, |source| {
+            let source = self.get_source_with_name(&source[1..]).ok_or(MeshError)?;
+            let mut values = vec![];
+            for color in source.iter() {
+                if color.len() < 3 {
+                    return Err(MeshError);
                 }
+                values.push(Vector3 { x: color[0], y: color[1], z: color[2] });
+            }
+            Ok(values)
+        })?;
 
-                let x = x.unwrap();
-                let y = y.unwrap(); 
-                let z = z.unwrap();
-
-                for normal in source.iter() {
-                    let vec = Vector3 { x: normal[x], y: normal[y], z: normal[z] };
-                    normals.push(vec);
-                }
+        for (group, remaps) in groups.iter().zip(remaps.iter()) {
+            for shape in shapes[group.start..group.start + group.count].iter_mut() {
+                shape.for_each_vertex_mut(|index| *index = remap_index(index, remaps));
             }
-            _ => {},
         }
 
-        let mut tex_coords = vec![];
-        match sources.1 {
-            Some(name) => {
-                let source = self.get_source_with_name(&name[1..]).ok_or(MeshError)?;
-                let param_names = source.get_parameter_names();
-                let mut s = None;
-                let mut t = None; 
-                for (i, name) in param_names.iter().enumerate() {
-                    match name.as_ref() {
-                        "S" => s = Some(i),
-                        "T" => t = Some(i),
-                        _ => {}
-                    }
-                }
+        Ok(GenericMesh {
+            vertices,
+            normals,
+            tex_coords,
+            colors,
+            tangents,
+            binormals,
+            shapes,
+            groups,
+        }) 
 
-                if s.is_none() || t.is_none() {
-                    return Err(Box::new(MeshError));
-                }
+    }
 
-                let s = s.unwrap();
-                let t = t.unwrap();
+    // Reads every value of a source, passing the parameters with the given names in order
+    fn read_source<V>(&self, name: &str, parameters: &[&str], read: impl Fn(&[f32]) -> V) -> Result<Vec<V>, MeshError> {
+        let source = self.get_source_with_name(&name[1..]).ok_or(MeshError)?;
+        let param_names = source.get_parameter_names();
 
-                for tex_coord in source.iter() {
-                    let vec = Vector2 { x: tex_coord[s], y: tex_coord[t] };
-                    tex_coords.push(vec);
-                }
+        let mut indices = vec![];
+        for parameter in parameters {
+            let index = param_names.iter().position(|name| name == parameter).ok_or(MeshError)?;
+            indices.push(index);
+        }
+
+        let mut values = vec![];
+        let mut value = vec![0.0; indices.len()];
+        for data in source.iter() {
+            for (i, index) in indices.iter().enumerate() {
+                value[i] = *data.get(*index).ok_or(MeshError)?;
             }
-            _ => {},
+            values.push(read(&value));
         }
 
-        let mut colors = vec![];
-        match sources.3 {
-            Some(name) => {
-                let source = self.get_source_with_name(&name[1..]).ok_or(MeshError)?;
-                for color in source.iter() {
-                    let vec = Vector3 { x: color[0], y: color[1], z: color[2] };
-                    colors.push(vec);
-                }
+        Ok(values)
+    }
+
+    // Collects the values of every source used with the semantic by any primitive element. Sets are
+    // ordered by set number and a source used by several elements is only read once. The remap of
+    // each input into the merged sets is added to the remaps of its element
+    fn merge_inputs<V>(&self, semantic: Semantic, remaps: &mut [Vec<InputRemap>], read: impl Fn(&str) -> Result<Vec<V>, MeshError>) -> Result<Vec<AttributeSet<V>>, MeshError> {
+        // Normals have a single set no matter what set number the elements give them
+        let set_of = |input: &SharedInput| if semantic == Semantic::Normal { 0 } else { input.set };
+
+        let mut sets: Vec<usize> = self.primitive_elements.iter()
+            .flat_map(|element| element.get_inputs().iter())
+            .filter(|input| input.semantic == semantic)
+            .map(set_of)
+            .collect();
+        sets.sort_unstable();
+        sets.dedup();
+
+        let mut attribute_sets: Vec<AttributeSet<V>> = sets.iter()
+            .map(|set| AttributeSet { set: *set, values: vec![] })
+            .collect();
+        // Slot, source and first index of every source read so far
+        let mut read_sources: Vec<(usize, &str, usize)> = vec![];
+
+        for (element, remaps) in self.primitive_elements.iter().zip(remaps.iter_mut()) {
+            for input in element.get_inputs().iter().filter(|input| input.semantic == semantic) {
+                let slot = sets.iter().position(|set| *set == set_of(input)).ok_or(MeshError)?;
+                let read_source = read_sources.iter().find(|(other_slot, source, _)| *other_slot == slot && *source == input.source());
+                let base = match read_source {
+                    Some((_, _, base)) => *base,
+                    None => {
+                        let values = read(input.source())?;
+                        let base = attribute_sets[slot].values.len();
+                        attribute_sets[slot].values.extend(values);
+                        read_sources.push((slot, input.source(), base));
+                        base
+                    }
+                };
+
+                remaps.push(InputRemap { semantic, from: input.slot(), to: slot, base });
             }
-            _ => {},
         }
-         
-        Ok(GenericMesh {
-            vertices,
-            normals,
-            tex_coords,
-            colors,
-            shapes
-        }) 
 
+        Ok(attribute_sets)
+    }
+}
+
+// Moves an index of a primitive element input to its slot and values in the merged mesh sets
+struct InputRemap {
+    semantic: Semantic,
+    from: usize,
+    to: usize,
+    base: usize,
+}
+
+fn remap_index(index: &PTNCIndex, remaps: &[InputRemap]) -> PTNCIndex {
+    let mut remapped = PTNCIndex::new(index.position);
+
+    for remap in remaps {
+        let (from, to) = match remap.semantic {
+            Semantic::Vertex => continue,
+            Semantic::Normal => {
+                remapped.normal = index.normal.map(|value| value + remap.base);
+                continue;
+            }
+            Semantic::TexCoord => (&index.tex_coords, &mut remapped.tex_coords),
+            Semantic::Color => (&index.colors, &mut remapped.colors),
+            Semantic::TexTangent => (&index.tangents, &mut remapped.tangents),
+            Semantic::TexBinormal => (&index.binormals, &mut remapped.binormals),
+        };
+        to.set(remap.to, from.get(remap.from).map(|value| value + remap.base));
     }
+
+    remapped
 }
 
 pub trait Vertex: Sized {
     fn from_attributes<'a>(attributes: impl Iterator<Item = (&'a str, &'a [String], Option<&'a [f32]>)>) -> Option<Self>;
 }
 
+// Vertices that have a position, needed by passes that work on the mesh geometry
+pub trait Position {
+    fn position(&self) -> Vector3;
+}
+
+// Values of an input with a `set` number, such as one of several texture coordinate channels
+#[derive(Clone, Debug)]
+pub struct AttributeSet<T> {
+    pub set: usize,
+    pub values: Vec<T>,
+}
+
+// Range of `shapes` that came from one primitive element, drawn with a single material
+#[derive(Clone, Debug)]
+pub struct PrimitiveGroup {
+    // Symbol of the material, bound to an actual material by the instance that uses the mesh
+    pub material: Option<String>,
+    pub start: usize,
+    pub count: usize,
+}
+
+// Rebuilds the shapes one group at a time so the groups keep covering the shapes they came from
+fn rebuild_shapes<T>(shapes: &mut Vec<Shape<T>>, groups: &mut [PrimitiveGroup], mut rebuild: impl FnMut(Shape<T>, &mut Vec<Shape<T>>)) {
+    let mut old_shapes = ::std::mem::take(shapes).into_iter();
+
+    for group in groups.iter_mut() {
+        let start = shapes.len();
+        for shape in old_shapes.by_ref().take(group.count) {
+            rebuild(shape, shapes);
+        }
+        group.start = start;
+        group.count = shapes.len() - start;
+    }
+
+    for shape in old_shapes {
+        rebuild(shape, shapes);
+    }
+}
+
 #[derive(Debug)]
 pub struct GenericMesh<T: Vertex> {
     pub vertices: Vec<T>,
     pub normals: Vec<Vector3>,
-    pub tex_coords: Vec<Vector2>,
-    pub colors: Vec<Vector3>,
-    pub shapes: Vec<Shape<PTNCIndex>>
+    // Ordered by set, the index of a set matches its slot in `PTNCIndex::tex_coords`
+    pub tex_coords: Vec<AttributeSet<Vector2>>,
+    // Ordered by set, the index of a set matches its slot in `PTNCIndex::colors`
+    pub colors: Vec<AttributeSet<Vector3>>,
+    // Texture space tangents and binormals, the set is the texture coordinate set they belong to
+    pub tangents: Vec<AttributeSet<Vector3>>,
+    pub binormals: Vec<AttributeSet<Vector3>>,
+    pub shapes: Vec<Shape<PTNCIndex>>,
+    pub groups: Vec<PrimitiveGroup>,
 }
 
 impl<T: Vertex> GenericMesh<T> {
@@ -214,4 +321,122 @@ impl<T: Vertex> GenericMesh<T> {
         let parser = MeshParser::parse_mesh(node, tree)?;
         Ok(parser.into_mesh()?)
     }
-}
\ No newline at end of file
+
+    pub fn tex_coord_slot(&self, set: usize) -> Option<usize> {
+        self.tex_coords.iter().position(|tex_coords| tex_coords.set == set)
+    }
+
+    pub fn tex_coord_set(&self, set: usize) -> Option<&[Vector2]> {
+        let slot = self.tex_coord_slot(set)?;
+        Some(self.tex_coords[slot].values.as_slice())
+    }
+
+    pub fn color_slot(&self, set: usize) -> Option<usize> {
+        self.colors.iter().position(|colors| colors.set == set)
+    }
+
+    pub fn color_set(&self, set: usize) -> Option<&[Vector3]> {
+        let slot = self.color_slot(set)?;
+        Some(self.colors[slot].values.as_slice())
+    }
+
+    pub fn tangent_slot(&self, set: usize) -> Option<usize> {
+        self.tangents.iter().position(|tangents| tangents.set == set)
+    }
+
+    pub fn tangent_set(&self, set: usize) -> Option<&[Vector3]> {
+        let slot = self.tangent_slot(set)?;
+        Some(self.tangents[slot].values.as_slice())
+    }
+
+    pub fn binormal_slot(&self, set: usize) -> Option<usize> {
+        self.binormals.iter().position(|binormals| binormals.set == set)
+    }
+
+    pub fn binormal_set(&self, set: usize) -> Option<&[Vector3]> {
+        let slot = self.binormal_slot(set)?;
+        Some(self.binormals[slot].values.as_slice())
+    }
+
+    // Material symbols used by the mesh in order of first use
+    pub fn materials(&self) -> Vec<Option<&str>> {
+        let mut materials = vec![];
+        for group in self.groups.iter() {
+            let material = group.material.as_ref().map(|material| &material[..]);
+            if !materials.contains(&material) {
+                materials.push(material);
+            }
+        }
+
+        materials
+    }
+
+    pub fn shapes_with_material<'a>(&'a self, material: Option<&'a str>) -> impl Iterator<Item = &'a Shape<PTNCIndex>> + 'a {
+        self.groups.iter()
+            .filter(move |group| group.material.as_ref().map(|material| &material[..]) == material)
+            .flat_map(move |group| self.shapes[group.start..group.start + group.count].iter())
+    }
+
+    // Replaces triangle strips, triangle fans and line strips with plain triangles and lines
+    pub fn expand_strips(&mut self) {
+        rebuild_shapes(&mut self.shapes, &mut self.groups, |shape, shapes| shapes.extend(shape.expand()));
+    }
+}
+
+impl<T: Vertex + Position> GenericMesh<T> {
+    // Replaces polygons, including those with holes, with triangles using ear clipping.
+    // Polygons with a position index out of range are kept as they are and an error is returned
+    pub fn triangulate(&mut self) -> Result<(), MeshError> {
+        let vertices = &self.vertices;
+        let mut result = Ok(());
+
+        let ring_positions = |ring: &[PTNCIndex]| -> Option<Vec<Vector3>> {
+            ring.iter()
+                .map(|index| vertices.get(index.position).map(|vertex| vertex.position()))
+                .collect()
+        };
+
+        rebuild_shapes(&mut self.shapes, &mut self.groups, |shape, shapes| {
+            let (outer, holes) = match shape {
+                Shape::Polygon(outer) => (outer, vec![]),
+                Shape::PolygonWithHoles(outer, holes) => (outer, holes),
+                shape => {
+                    shapes.push(shape);
+                    return;
+                }
+            };
+
+            let outer_positions = ring_positions(&outer);
+            let hole_positions: Option<Vec<Vec<Vector3>>> = holes.iter()
+                .map(|hole| ring_positions(hole))
+                .collect();
+            let (outer_positions, hole_positions) = match (outer_positions, hole_positions) {
+                (Some(outer_positions), Some(hole_positions)) => (outer_positions, hole_positions),
+                _ => {
+                    result = Err(MeshError);
+                    shapes.push(match holes.is_empty() {
+                        true => Shape::Polygon(outer),
+                        false => Shape::PolygonWithHoles(outer, holes),
+                    });
+                    return;
+                }
+            };
+
+            if outer.len() == 3 && holes.is_empty() {
+                shapes.push(Shape::Triangle(outer[0].clone(), outer[1].clone(), outer[2].clone()));
+                return;
+            }
+
+            let corners: Vec<PTNCIndex> = outer.iter()
+                .chain(holes.iter().flat_map(|hole| hole.iter()))
+                .cloned()
+                .collect();
+
+            for [a, b, c] in triangulate::triangulate_polygon(&outer_positions, &hole_positions) {
+                shapes.push(Shape::Triangle(corners[a].clone(), corners[b].clone(), corners[c].clone()));
+            }
+        });
+
+        result
+    }
+}
@@ -0,0 +1,131 @@
+use collada::{GenericMesh, Shape};
+use math::Vector3;
+use super::{Vertex, Position};
+use std::collections::HashMap;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum NormalMode {
+    // One normal per triangle
+    Flat,
+    // Corners sharing a position average the normals of the triangles around them, weighted by
+    // the corner angles. Triangles whose normals differ by more than the crease angle (in radians)
+    // do not contribute, keeping hard edges sharp
+    Smooth { crease_angle: f32 },
+}
+
+// Normal and corner angles of one triangle
+struct Face {
+    normal: Vector3,
+    angles: [f32; 3],
+}
+
+// None when the triangle is a sliver. The edges are divided by the longest one first so
+// small triangles are not mistaken for degenerate ones
+fn face_normal(p0: Vector3, p1: Vector3, p2: Vector3) -> Option<Vector3> {
+    let longest = (p1 - p0).length().max((p2 - p1).length()).max((p0 - p2).length());
+    if longest == 0.0 {
+        return None;
+    }
+
+    let scale = 1.0 / longest;
+    ((p1 - p0) * scale).cross((p2 - p0) * scale).normalize()
+}
+
+fn corner_angle(corner: Vector3, next: Vector3, prev: Vector3) -> f32 {
+    match ((next - corner).normalize(), (prev - corner).normalize()) {
+        (Some(a), Some(b)) => a.dot(b).clamp(-1.0, 1.0).acos(),
+        _ => 0.0,
+    }
+}
+
+impl<T: Vertex + Position> GenericMesh<T> {
+    // Replaces the normals of the mesh with generated ones, for meshes exported without a NORMAL input.
+    // Only triangles get normals so polygons should be triangulated first
+    pub fn generate_normals(&mut self, mode: NormalMode) {
+        let mut faces: Vec<Face> = vec![];
+        for shape in self.shapes.iter() {
+            let (v0, v1, v2) = match shape {
+                Shape::Triangle(v0, v1, v2) => (v0, v1, v2),
+                _ => continue,
+            };
+
+            let zero = Vector3 { x: 0.0, y: 0.0, z: 0.0 };
+            let p0 = self.vertices.get(v0.position).map(|vertex| vertex.position()).unwrap_or(zero);
+            let p1 = self.vertices.get(v1.position).map(|vertex| vertex.position()).unwrap_or(zero);
+            let p2 = self.vertices.get(v2.position).map(|vertex| vertex.position()).unwrap_or(zero);
+
+            faces.push(Face {
+                normal: face_normal(p0, p1, p2).unwrap_or(zero),
+                angles: [
+                    corner_angle(p0, p1, p2),
+                    corner_angle(p1, p2, p0),
+                    corner_angle(p2, p0, p1),
+                ],
+            });
+        }
+
+        let mut normals: Vec<Vector3> = vec![];
+        // Normal index of every triangle corner in order
+        let mut corner_normals: Vec<usize> = Vec::with_capacity(faces.len() * 3);
+
+        match mode {
+            NormalMode::Flat => for face in faces.iter() {
+                let index = normals.len();
+                normals.push(face.normal);
+                corner_normals.extend_from_slice(&[index; 3]);
+            }
+            NormalMode::Smooth { crease_angle } => {
+                let min_cos = crease_angle.cos();
+
+                // Faces and corners around each position
+                let mut adjacency: HashMap<usize, Vec<(usize, usize)>> = HashMap::new();
+                let triangles = self.shapes.iter().filter_map(|shape| match shape {
+                    Shape::Triangle(v0, v1, v2) => Some([v0.position, v1.position, v2.position]),
+                    _ => None,
+                });
+                let triangles: Vec<[usize; 3]> = triangles.collect();
+                for (face, triangle) in triangles.iter().enumerate() {
+                    for (corner, position) in triangle.iter().enumerate() {
+                        adjacency.entry(*position).or_default().push((face, corner));
+                    }
+                }
+
+                let mut unique: HashMap<(usize, [u32; 3]), usize> = HashMap::new();
+                for (face, triangle) in triangles.iter().enumerate() {
+                    for position in triangle.iter() {
+                        let face_normal = faces[face].normal;
+                        let mut sum = Vector3 { x: 0.0, y: 0.0, z: 0.0 };
+
+                        for (other, corner) in adjacency[position].iter() {
+                            let other_face = &faces[*other];
+                            if face_normal.dot(other_face.normal) >= min_cos {
+                                sum = sum + other_face.normal * other_face.angles[*corner];
+                            }
+                        }
+
+                        let normal = sum.normalize().unwrap_or(face_normal);
+                        let key = (*position, [normal.x.to_bits(), normal.y.to_bits(), normal.z.to_bits()]);
+                        let next = normals.len();
+                        let index = *unique.entry(key).or_insert(next);
+                        if index == next {
+                            normals.push(normal);
+                        }
+                        corner_normals.push(index);
+                    }
+                }
+            }
+        }
+
+        let mut corner_normals = corner_normals.into_iter();
+        for shape in self.shapes.iter_mut() {
+            if let Shape::Triangle(..) = shape {
+                shape.for_each_vertex_mut(|index| index.normal = corner_normals.next());
+            }
+            else {
+                shape.for_each_vertex_mut(|index| index.normal = None);
+            }
+        }
+
+        self.normals = normals;
+    }
+}
@@ -33,8 +33,8 @@ impl PrimitiveIndices {
         self.accessor.get_nth_ptn(n, &self.indices[..])
     }
 
-    pub fn get_ptnc_sources(&self) -> (&str, Option<&str>, Option<&str>, Option<&str>) {
-        self.accessor.ptnc_sources()
+    pub fn get_inputs(&self) -> &[SharedInput] {
+        self.accessor.inputs()
     }
 
     pub fn get_ptn_sources(&self) -> (&str, Option<&str>, Option<&str>) {
@@ -62,19 +62,94 @@ impl PrimitiveIndices {
     }
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub enum Shape<T> {
     Triangle(T, T, T),
     Line(T, T),
+    Polygon(Vec<T>),
+    // Outer ring followed by the rings of each hole
+    PolygonWithHoles(Vec<T>, Vec<Vec<T>>),
     TriFan(Vec<T>),
     TriStrips(Vec<T>),
+    LineStrip(Vec<T>),
+}
+
+impl<T> Shape<T> {
+    pub fn for_each_vertex_mut(&mut self, mut f: impl FnMut(&mut T)) {
+        match self {
+            Shape::Triangle(v0, v1, v2) => {
+                f(v0);
+                f(v1);
+                f(v2);
+            }
+            Shape::Line(v0, v1) => {
+                f(v0);
+                f(v1);
+            }
+            Shape::PolygonWithHoles(outer, holes) => {
+                outer.iter_mut().for_each(&mut f);
+                holes.iter_mut().flat_map(|hole| hole.iter_mut()).for_each(&mut f);
+            }
+            Shape::Polygon(vertices)
+            | Shape::TriFan(vertices)
+            | Shape::TriStrips(vertices)
+            | Shape::LineStrip(vertices) => vertices.iter_mut().for_each(f),
+        }
+    }
+}
+
+impl<T: Clone + PartialEq> Shape<T> {
+    // Splits strips and fans into separate triangles or lines, skipping degenerate triangles.
+    // Other shapes are returned unchanged
+    pub fn expand(&self) -> Vec<Shape<T>> {
+        match self {
+            Shape::TriFan(vertices) => {
+                let mut shapes = vec![];
+                for i in 1..vertices.len().saturating_sub(1) {
+                    let (v0, v1, v2) = (&vertices[0], &vertices[i], &vertices[i + 1]);
+                    if v0 != v1 && v1 != v2 && v0 != v2 {
+                        shapes.push(Shape::Triangle(v0.clone(), v1.clone(), v2.clone()));
+                    }
+                }
+                shapes
+            }
+            Shape::TriStrips(vertices) => {
+                let mut shapes = vec![];
+                for i in 0..vertices.len().saturating_sub(2) {
+                    // Every second triangle has its winding flipped to keep a consistent facing
+                    let (v0, v1, v2) = match i % 2 {
+                        0 => (&vertices[i], &vertices[i + 1], &vertices[i + 2]),
+                        _ => (&vertices[i + 1], &vertices[i], &vertices[i + 2]),
+                    };
+                    if v0 != v1 && v1 != v2 && v0 != v2 {
+                        shapes.push(Shape::Triangle(v0.clone(), v1.clone(), v2.clone()));
+                    }
+                }
+                shapes
+            }
+            Shape::LineStrip(vertices) => {
+                vertices.windows(2).map(|line| Shape::Line(line[0].clone(), line[1].clone())).collect()
+            }
+            shape => vec![shape.clone()],
+        }
+    }
 }
 
 #[derive(Debug)]
 pub enum PrimitiveType {
     Triangles,
     Lines,
-    // Add more
+    Polylist,
+    Polygons,
+    TriStrips,
+    TriFans,
+    LineStrips,
+}
+
+// Vertex counts of the outer ring and holes of a single polygon
+struct PolygonRings {
+    outer: usize,
+    holes: Vec<usize>,
 }
 
 #[derive(Debug)]
@@ -82,6 +157,14 @@ pub struct PrimitiveElement {
     count: usize, 
     indices: PrimitiveIndices,
     p_type: PrimitiveType,
+    // Symbol bound to a material by the instance that uses the mesh
+    material: Option<String>,
+    // Number of vertices in each primitive, only used by primitives with a variable vertex count
+    vcount: Vec<usize>,
+    // Number of vertices in each hole of each primitive, only used by polygons
+    holes: Vec<Vec<usize>>,
+    // Index of the first vertex of each primitive in `indices`
+    starts: Vec<usize>,
 }
 
 impl PrimitiveElement {
@@ -89,6 +172,14 @@ impl PrimitiveElement {
         self.count
     }
 
+    pub fn material(&self) -> Option<&str> {
+        self.material.as_ref().map(|material| &material[..])
+    }
+
+    pub fn vertex_counts(&self) -> &[usize] {
+        self.vcount.as_slice()
+    }
+
     pub fn parse_primitive_element(node: &XmlNode, tree: &XmlTree) -> Result<PrimitiveElement, Box<dyn Error>> {
         let count = node.get_attribute_with_name("count").ok_or(PrimitiveElementError)?;
         let count: usize = count.parse()?;
@@ -96,76 +187,206 @@ impl PrimitiveElement {
         let p_type = match node.name.local_name.as_ref() {
             "triangles" => PrimitiveType::Triangles,
             "lines" => PrimitiveType::Lines,
+            "polylist" => PrimitiveType::Polylist,
+            "polygons" => PrimitiveType::Polygons,
+            "tristrips" => PrimitiveType::TriStrips,
+            "trifans" => PrimitiveType::TriFans,
+            "linestrips" => PrimitiveType::LineStrips,
             _ => return Err(Box::new(PrimitiveElementError)),
         };
 
-        let indices = PrimitiveIndices::parse_indices(node, tree)?;
+        let mut vcount: Vec<usize> = vec![];
+        let mut holes: Vec<Vec<usize>> = vec![];
+        let indices = match p_type {
+            PrimitiveType::Polylist => {
+                let indices = PrimitiveIndices::parse_indices(node, tree)?;
+                // Empty polylists may leave out the vcount, the count check below catches
+                // polylists that have primitives but no vertex counts
+                if let Some(vcount_node) = node.get_children_with_name("vcount", tree).next() {
+                    vcount = parse_array(vcount_node.get_characters().unwrap_or(""))?;
+                }
+                indices
+            }
+            PrimitiveType::Polygons => {
+                let (indices, rings) = PrimitiveElement::parse_rings(node, tree, true)?;
+                for ring in rings {
+                    vcount.push(ring.outer);
+                    holes.push(ring.holes);
+                }
+                indices
+            }
+            PrimitiveType::TriStrips | PrimitiveType::TriFans | PrimitiveType::LineStrips => {
+                let (indices, rings) = PrimitiveElement::parse_rings(node, tree, false)?;
+                vcount.extend(rings.iter().map(|ring| ring.outer));
+                indices
+            }
+            _ => PrimitiveIndices::parse_indices(node, tree)?,
+        };
+
+        let mut starts = Vec::with_capacity(vcount.len());
+        let mut start = 0;
+        for (i, vertex_count) in vcount.iter().enumerate() {
+            starts.push(start);
+            start += vertex_count;
+            if let Some(primitive_holes) = holes.get(i) {
+                start += primitive_holes.iter().sum::<usize>();
+            }
+        }
+
+        // Primitives with a variable vertex count must list exactly count primitives
+        let variable_count = !matches!(p_type, PrimitiveType::Triangles | PrimitiveType::Lines);
+        if variable_count && (vcount.len() != count || start != indices.len()) {
+            return Err(Box::new(PrimitiveElementError));
+        }
         
+        let material = node.get_attribute_with_name("material").map(|material| material.to_string());
+
         Ok(PrimitiveElement {
             count,
             indices,
-            p_type
+            p_type,
+            material,
+            vcount,
+            holes,
+            starts,
         })
     }
 
-    pub fn get_nth_ptnc_element(&self, n: usize) -> Option<Shape<PTNCIndex>> {
-        match self.p_type {
-            PrimitiveType::Triangles => {
-                let start = n * 3;
-                if start + 3 > self.indices.element_count {
-                    return None;
-                }
-
-                let v0 = self.indices.get_nth_ptnc(start)?; 
-                let v1 = self.indices.get_nth_ptnc(start + 1)?; 
-                let v2 = self.indices.get_nth_ptnc(start + 2)?; 
-
-                Some(Shape::Triangle(v0, v1, v2))
+    // Reads every <p> and <ph> child as a separate primitive, returning the number of vertices 
+    // in the outer ring and in each hole of every primitive. Only polygons may have <ph> children
+    fn parse_rings(node: &XmlNode, tree: &XmlTree, allow_holes: bool) -> Result<(PrimitiveIndices, Vec<PolygonRings>), Box<dyn Error>> {
+        let accessor = IndexAccessor::parse_accessor(node, tree)?;
+        let components = accessor.components;
+        let mut indices: Vec<usize> = vec![];
+        let mut rings = vec![];
+
+        let parse_ring = |node: &XmlNode, indices: &mut Vec<usize>| -> Result<usize, Box<dyn Error>> {
+            let ring: Vec<usize> = parse_array(node.get_characters().unwrap_or(""))?;
+            if !ring.len().is_multiple_of(components) {
+                return Err(Box::new(PrimitiveIndicesError));
             }
-            PrimitiveType::Lines => {
-                let start = n * 2;
-                if start + 2 > self.count {
-                    return None;
-                }
+            indices.extend(ring.iter());
 
-                let v0 = self.indices.get_nth_ptnc(start)?; 
-                let v1 = self.indices.get_nth_ptnc(start + 1)?; 
+            Ok(ring.len() / components)
+        };
 
-                Some(Shape::Line(v0, v1))
+        for child in tree.nodes_iter(node.get_children()) {
+            let child = child.unwrap();
+
+            match child.name.local_name.as_ref() {
+                "p" => {
+                    let outer = parse_ring(child, &mut indices)?;
+                    rings.push(PolygonRings { outer, holes: vec![] });
+                }
+                "ph" if !allow_holes => return Err(Box::new(PrimitiveElementError)),
+                "ph" => {
+                    let mut outer = None;
+                    let mut primitive_holes = vec![];
+
+                    for ring in tree.nodes_iter(child.get_children()) {
+                        let ring = ring.unwrap();
+
+                        match ring.name.local_name.as_ref() {
+                            "p" if outer.is_none() => outer = Some(parse_ring(ring, &mut indices)?),
+                            "h" if outer.is_some() => primitive_holes.push(parse_ring(ring, &mut indices)?),
+                            _ => return Err(Box::new(PrimitiveElementError)),
+                        }
+                    }
+
+                    let outer = outer.ok_or(PrimitiveElementError)?;
+                    rings.push(PolygonRings { outer, holes: primitive_holes });
+                }
+                _ => {}
             }
         }
+
+        Ok((PrimitiveIndices::new(accessor, indices)?, rings))
     }
 
-    pub fn get_nth_ptn_element(&self, n: usize) -> Option<Shape<PTNIndex>> {
+    fn get_nth_element<T>(&self, n: usize, get_nth: impl Fn(usize) -> Option<T>) -> Option<Shape<T>> {
+        if n >= self.count {
+            return None;
+        }
+
         match self.p_type {
             PrimitiveType::Triangles => {
                 let start = n * 3;
-                if start + 3 > self.count {
+                if start + 3 > self.indices.len() {
                     return None;
                 }
 
-                let v0 = self.indices.get_nth_ptn(start)?; 
-                let v1 = self.indices.get_nth_ptn(start + 1)?; 
-                let v2 = self.indices.get_nth_ptn(start + 2)?; 
+                let v0 = get_nth(start)?; 
+                let v1 = get_nth(start + 1)?; 
+                let v2 = get_nth(start + 2)?; 
 
                 Some(Shape::Triangle(v0, v1, v2))
             }
             PrimitiveType::Lines => {
                 let start = n * 2;
-                if start + 2 > self.count {
+                if start + 2 > self.indices.len() {
                     return None;
                 }
 
-                let v0 = self.indices.get_nth_ptn(start)?; 
-                let v1 = self.indices.get_nth_ptn(start + 1)?; 
+                let v0 = get_nth(start)?; 
+                let v1 = get_nth(start + 1)?; 
 
                 Some(Shape::Line(v0, v1))
             }
+            PrimitiveType::Polylist => {
+                let start = self.starts[n];
+                let vertices = (start..start + self.vcount[n])
+                    .map(get_nth)
+                    .collect::<Option<Vec<T>>>()?;
+
+                Some(Shape::Polygon(vertices))
+            }
+            PrimitiveType::Polygons => {
+                let mut start = self.starts[n];
+                let outer = (start..start + self.vcount[n])
+                    .map(&get_nth)
+                    .collect::<Option<Vec<T>>>()?;
+
+                if self.holes[n].is_empty() {
+                    return Some(Shape::Polygon(outer));
+                }
+
+                start += self.vcount[n];
+                let mut holes = Vec::with_capacity(self.holes[n].len());
+                for hole_count in self.holes[n].iter() {
+                    let hole = (start..start + hole_count)
+                        .map(&get_nth)
+                        .collect::<Option<Vec<T>>>()?;
+                    holes.push(hole);
+                    start += hole_count;
+                }
+
+                Some(Shape::PolygonWithHoles(outer, holes))
+            }
+            PrimitiveType::TriStrips | PrimitiveType::TriFans | PrimitiveType::LineStrips => {
+                let start = self.starts[n];
+                let vertices = (start..start + self.vcount[n])
+                    .map(get_nth)
+                    .collect::<Option<Vec<T>>>()?;
+
+                match self.p_type {
+                    PrimitiveType::TriStrips => Some(Shape::TriStrips(vertices)),
+                    PrimitiveType::TriFans => Some(Shape::TriFan(vertices)),
+                    _ => Some(Shape::LineStrip(vertices)),
+                }
+            }
         }
     }
 
-    pub fn get_ptnc_sources(&self) -> (&str, Option<&str>, Option<&str>, Option<&str>) {
-        self.indices.get_ptnc_sources()
+    pub fn get_nth_ptnc_element(&self, n: usize) -> Option<Shape<PTNCIndex>> {
+        self.get_nth_element(n, |i| self.indices.get_nth_ptnc(i))
+    }
+
+    pub fn get_nth_ptn_element(&self, n: usize) -> Option<Shape<PTNIndex>> {
+        self.get_nth_element(n, |i| self.indices.get_nth_ptn(i))
+    }
+
+    pub fn get_inputs(&self) -> &[SharedInput] {
+        self.indices.get_inputs()
     }
 
     pub fn get_ptn_sources(&self) -> (&str, Option<&str>, Option<&str>) {
@@ -0,0 +1,402 @@
+use collada::{GenericMesh, Shape, PTNCIndex, SetIndices, error::*};
+use math::{Vector3, Vector4};
+use super::{Vertex, Position, AttributeSet};
+use std::collections::HashMap;
+
+// Cosine of the angle between triangle tangents above which they share a tangent space,
+// MikkTSpace uses 180 degrees by default so only opposite tangents are split
+const SPLIT_COS: f32 = -1.0;
+
+// Per triangle data, the MikkTSpace STriInfo
+struct TriangleInfo {
+    // Triangle across the edge from each corner to the next one
+    neighbors: [Option<usize>; 3],
+    // Group each corner belongs to
+    groups: [Option<usize>; 3],
+    // Unit tangent and binormal from the texture coordinate derivatives, the tangent flipped
+    // for mirrored texture coordinates
+    os: Vector3,
+    ot: Vector3,
+    orientation_preserving: bool,
+    // The texture coordinates are degenerate so the triangle takes the tangent space of its neighbors
+    group_with_any: bool,
+}
+
+// Connected triangles sharing a welded vertex with the same handedness
+struct Group {
+    vertex: usize,
+    orientation_preserving: bool,
+    triangles: Vec<usize>,
+}
+
+#[derive(Copy, Clone)]
+struct TangentSpace {
+    tangent: Vector3,
+    orientation_preserving: bool,
+}
+
+fn not_zero(value: f32) -> bool {
+    value.abs() > f32::MIN_POSITIVE
+}
+
+fn vector_not_zero(vector: Vector3) -> bool {
+    not_zero(vector.x) || not_zero(vector.y) || not_zero(vector.z)
+}
+
+// Normalizes unless every component is zero, like MikkTSpace does
+fn normalize(vector: Vector3) -> Vector3 {
+    match vector_not_zero(vector) {
+        true => vector * (1.0 / vector.length()),
+        false => vector,
+    }
+}
+
+fn project(vector: Vector3, normal: Vector3) -> Vector3 {
+    normalize(vector - normal * normal.dot(vector))
+}
+
+// Makes negative zero equal to zero so welding matches float comparison
+fn weld_bits(value: f32) -> u32 {
+    (value + 0.0).to_bits()
+}
+
+// Corners of the edge between the vertices in triangle winding order, and the edge number
+fn edge(vertices: &[usize], triangle: usize, a: usize, b: usize) -> (usize, usize, usize) {
+    let corners = &vertices[triangle * 3..triangle * 3 + 3];
+    let on_edge = |vertex: usize| vertex == a || vertex == b;
+    if on_edge(corners[0]) {
+        if on_edge(corners[1]) {
+            (corners[0], corners[1], 0)
+        }
+        else {
+            (corners[2], corners[0], 2)
+        }
+    }
+    else {
+        (corners[1], corners[2], 1)
+    }
+}
+
+// Adds the triangle and every triangle connected to it through the group vertex to the group,
+// as long as they agree on handedness
+fn assign_group(vertices: &[usize], infos: &mut [TriangleInfo], groups: &mut [Group], group: usize, triangle: usize) {
+    let mut pending = vec![triangle];
+    while let Some(triangle) = pending.pop() {
+        let corner = match (0..3).find(|&i| vertices[triangle * 3 + i] == groups[group].vertex) {
+            Some(corner) => corner,
+            None => continue,
+        };
+
+        let info = &mut infos[triangle];
+        if info.groups[corner].is_some() {
+            continue;
+        }
+
+        // The first group reaching a triangle with degenerate texture coordinates decides its handedness
+        if info.group_with_any && info.groups.iter().all(|group| group.is_none()) {
+            info.orientation_preserving = groups[group].orientation_preserving;
+        }
+        if info.orientation_preserving != groups[group].orientation_preserving {
+            continue;
+        }
+
+        groups[group].triangles.push(triangle);
+        info.groups[corner] = Some(group);
+
+        // Visits the neighbor after the corner first, then the one before it
+        if let Some(neighbor) = info.neighbors[(corner + 2) % 3] {
+            pending.push(neighbor);
+        }
+        if let Some(neighbor) = info.neighbors[corner] {
+            pending.push(neighbor);
+        }
+    }
+}
+
+// Finds the slot of the set, inserting an empty set in set order and moving the indices
+// of every later slot when it does not exist yet
+fn set_slot(sets: &mut Vec<AttributeSet<Vector3>>, set: usize, shapes: &mut [Shape<PTNCIndex>], slots: fn(&mut PTNCIndex) -> &mut SetIndices) -> usize {
+    if let Some(slot) = sets.iter().position(|values| values.set == set) {
+        return slot;
+    }
+
+    let slot = sets.iter().filter(|values| values.set < set).count();
+    sets.insert(slot, AttributeSet { set, values: vec![] });
+
+    for shape in shapes.iter_mut() {
+        shape.for_each_vertex_mut(|index| {
+            slots(index).insert_slot(slot);
+        });
+    }
+
+    slot
+}
+
+// Tangent space of every corner of a list of triangles, a port of genTangSpace from the MikkTSpace
+// reference implementation restricted to triangles
+fn mikktspace(positions: &[Vector3], normals: &[Vector3], uvs: &[(f32, f32)]) -> Vec<TangentSpace> {
+    let zero = Vector3 { x: 0.0, y: 0.0, z: 0.0 };
+    let triangle_count = positions.len() / 3;
+
+    // Welds every corner to the first one with the same position, normal and texture coordinate
+    let mut welded: HashMap<[u32; 8], usize> = HashMap::new();
+    let vertices: Vec<usize> = (0..triangle_count * 3).map(|corner| {
+        let (position, normal, uv) = (positions[corner], normals[corner], uvs[corner]);
+        let key = [
+            weld_bits(position.x), weld_bits(position.y), weld_bits(position.z),
+            weld_bits(normal.x), weld_bits(normal.y), weld_bits(normal.z),
+            weld_bits(uv.0), weld_bits(uv.1),
+        ];
+        *welded.entry(key).or_insert(corner)
+    }).collect();
+
+    // Triangles with two corners at the same position copy the tangent spaces of the other triangles
+    let same_position = |a: Vector3, b: Vector3| a.x == b.x && a.y == b.y && a.z == b.z;
+    let (good, degenerate): (Vec<usize>, Vec<usize>) = (0..triangle_count).partition(|&triangle| {
+        let corner = triangle * 3;
+        let (p0, p1, p2) = (positions[vertices[corner]], positions[vertices[corner + 1]], positions[vertices[corner + 2]]);
+        !(same_position(p0, p1) || same_position(p0, p2) || same_position(p1, p2))
+    });
+
+    let mut infos: Vec<TriangleInfo> = (0..triangle_count).map(|triangle| {
+        let corner = triangle * 3;
+        let (v0, v1, v2) = (vertices[corner], vertices[corner + 1], vertices[corner + 2]);
+        let d1 = positions[v1] - positions[v0];
+        let d2 = positions[v2] - positions[v0];
+        let (s1, t1) = (uvs[v1].0 - uvs[v0].0, uvs[v1].1 - uvs[v0].1);
+        let (s2, t2) = (uvs[v2].0 - uvs[v0].0, uvs[v2].1 - uvs[v0].1);
+        let signed_area = s1 * t2 - s2 * t1;
+
+        let mut info = TriangleInfo {
+            neighbors: [None; 3],
+            groups: [None; 3],
+            os: zero,
+            ot: zero,
+            orientation_preserving: signed_area > 0.0,
+            group_with_any: true,
+        };
+
+        if not_zero(signed_area) {
+            let sign = if info.orientation_preserving { 1.0 } else { -1.0 };
+            let os = d1 * t2 - d2 * t1;
+            let ot = d2 * s1 - d1 * s2;
+            if not_zero(os.length()) {
+                info.os = os * (sign / os.length());
+            }
+            if not_zero(ot.length()) {
+                info.ot = ot * (sign / ot.length());
+            }
+            info.group_with_any = !(vector_not_zero(info.os) && vector_not_zero(info.ot));
+        }
+
+        info
+    }).collect();
+
+    // Triangles are neighbors when they share an edge with opposite winding
+    let mut edges: Vec<(usize, usize, usize)> = vec![];
+    for &triangle in good.iter() {
+        for i in 0..3 {
+            let (a, b) = (vertices[triangle * 3 + i], vertices[triangle * 3 + (i + 1) % 3]);
+            edges.push((a.min(b), a.max(b), triangle));
+        }
+    }
+    edges.sort();
+
+    for (i, &(a, b, triangle)) in edges.iter().enumerate() {
+        let (first, second, edge_number) = edge(&vertices, triangle, a, b);
+        if infos[triangle].neighbors[edge_number].is_some() {
+            continue;
+        }
+
+        let same_edge = edges[i + 1..].iter().take_while(|&&(other_a, other_b, _)| other_a == a && other_b == b);
+        for &(_, _, other) in same_edge {
+            let (other_first, other_second, other_edge_number) = edge(&vertices, other, a, b);
+            if first == other_second && second == other_first && infos[other].neighbors[other_edge_number].is_none() {
+                infos[triangle].neighbors[edge_number] = Some(other);
+                infos[other].neighbors[other_edge_number] = Some(triangle);
+                break;
+            }
+        }
+    }
+
+    let mut groups: Vec<Group> = vec![];
+    for &triangle in good.iter() {
+        for corner in 0..3 {
+            if infos[triangle].group_with_any || infos[triangle].groups[corner].is_some() {
+                continue;
+            }
+
+            groups.push(Group {
+                vertex: vertices[triangle * 3 + corner],
+                orientation_preserving: infos[triangle].orientation_preserving,
+                triangles: vec![],
+            });
+            let group = groups.len() - 1;
+            assign_group(&vertices, &mut infos, &mut groups, group, triangle);
+        }
+    }
+
+    // Corners without a group keep the default tangent space of MikkTSpace
+    let mut spaces = vec![TangentSpace { tangent: Vector3 { x: 1.0, y: 0.0, z: 0.0 }, orientation_preserving: false }; triangle_count * 3];
+
+    let corner_of = |triangle: usize, vertex: usize| (0..3).find(|&i| vertices[triangle * 3 + i] == vertex).unwrap_or(0);
+
+    // Angle weighted average of the projected tangents of the triangles around the vertex
+    let evaluate = |triangles: &[usize], vertex: usize| {
+        let normal = normals[vertex];
+        let mut sum = zero;
+        for &triangle in triangles.iter().filter(|&&triangle| !infos[triangle].group_with_any) {
+            let corner = corner_of(triangle, vertex);
+            let current = positions[vertex];
+            let prev = positions[vertices[triangle * 3 + (corner + 2) % 3]];
+            let next = positions[vertices[triangle * 3 + (corner + 1) % 3]];
+            let angle = project(prev - current, normal).dot(project(next - current, normal)).clamp(-1.0, 1.0).acos();
+            sum = sum + project(infos[triangle].os, normal) * angle;
+        }
+        normalize(sum)
+    };
+
+    for (index, group) in groups.iter().enumerate() {
+        let normal = normals[group.vertex];
+        // Triangles in a group are split into subgroups of triangles with similar tangents
+        let mut subgroups: Vec<(Vec<usize>, Vector3)> = vec![];
+
+        for &triangle in group.triangles.iter() {
+            let corner = (0..3).find(|&i| infos[triangle].groups[i] == Some(index)).unwrap_or(0);
+            let info = &infos[triangle];
+            let (os, ot) = (project(info.os, normal), project(info.ot, normal));
+
+            let mut members: Vec<usize> = group.triangles.iter().cloned().filter(|&other| {
+                let other_info = &infos[other];
+                let similar = os.dot(project(other_info.os, normal)) > SPLIT_COS && ot.dot(project(other_info.ot, normal)) > SPLIT_COS;
+                info.group_with_any || other_info.group_with_any || other == triangle || similar
+            }).collect();
+            members.sort();
+
+            let subgroup = match subgroups.iter().position(|subgroup| subgroup.0 == members) {
+                Some(subgroup) => subgroup,
+                None => {
+                    let tangent = evaluate(&members, group.vertex);
+                    subgroups.push((members, tangent));
+                    subgroups.len() - 1
+                }
+            };
+
+            spaces[triangle * 3 + corner] = TangentSpace {
+                tangent: subgroups[subgroup].1,
+                orientation_preserving: group.orientation_preserving,
+            };
+        }
+    }
+
+    // Degenerate triangles copy the tangent space of the first good corner welded to theirs
+    let mut good_corners: HashMap<usize, usize> = HashMap::new();
+    for &triangle in good.iter() {
+        for (corner, &vertex) in vertices.iter().enumerate().skip(triangle * 3).take(3) {
+            good_corners.entry(vertex).or_insert(corner);
+        }
+    }
+    for &triangle in degenerate.iter() {
+        for corner in triangle * 3..triangle * 3 + 3 {
+            if let Some(&source) = good_corners.get(&vertices[corner]) {
+                spaces[corner] = spaces[source];
+            }
+        }
+    }
+
+    spaces
+}
+
+impl<T: Vertex + Position> GenericMesh<T> {
+    // Generates per corner tangents and binormals for a texture coordinate set with the MikkTSpace
+    // algorithm, so normal maps baked with it look right.
+    // The binormal is the cross product of the normal and tangent, flipped for mirrored texture coordinates.
+    // Only triangles are processed so polygons should be triangulated first
+    pub fn generate_tangents(&mut self, set: usize) -> Result<(), MeshError> {
+        let tex_coord_slot = self.tex_coord_slot(set).ok_or(MeshError)?;
+        let mut positions: Vec<Vector3> = vec![];
+        let mut normals: Vec<Vector3> = vec![];
+        let mut uvs: Vec<(f32, f32)> = vec![];
+
+        {
+            let tex_coords = &self.tex_coords[tex_coord_slot].values;
+
+            for shape in self.shapes.iter() {
+                let triangle = match shape {
+                    Shape::Triangle(v0, v1, v2) => [v0, v1, v2],
+                    _ => continue,
+                };
+
+                for index in triangle.iter() {
+                    positions.push(self.vertices.get(index.position).ok_or(MeshError)?.position());
+                    normals.push(*index.normal.and_then(|i| self.normals.get(i)).ok_or(MeshError)?);
+                    let uv = index.tex_coords.get(tex_coord_slot).and_then(|i| tex_coords.get(i)).ok_or(MeshError)?;
+                    uvs.push((uv.x, uv.y));
+                }
+            }
+        }
+
+        let spaces = mikktspace(&positions, &normals, &uvs);
+
+        let mut unique: HashMap<[u32; 6], usize> = HashMap::new();
+        let mut tangents = vec![];
+        let mut binormals = vec![];
+        let mut corner_spaces = Vec::with_capacity(spaces.len());
+        for (space, normal) in spaces.iter().zip(normals.iter()) {
+            let sign = if space.orientation_preserving { 1.0 } else { -1.0 };
+            let tangent = space.tangent;
+            let binormal = normal.cross(tangent) * sign;
+
+            let key = [
+                tangent.x.to_bits(), tangent.y.to_bits(), tangent.z.to_bits(),
+                binormal.x.to_bits(), binormal.y.to_bits(), binormal.z.to_bits(),
+            ];
+            let next = tangents.len();
+            let index = *unique.entry(key).or_insert(next);
+            if index == next {
+                tangents.push(tangent);
+                binormals.push(binormal);
+            }
+            corner_spaces.push(index);
+        }
+
+        let tangent_slot = set_slot(&mut self.tangents, set, &mut self.shapes, |index| &mut index.tangents);
+        let binormal_slot = set_slot(&mut self.binormals, set, &mut self.shapes, |index| &mut index.binormals);
+        self.tangents[tangent_slot].values = tangents;
+        self.binormals[binormal_slot].values = binormals;
+
+        let mut corner_spaces = corner_spaces.into_iter();
+        for shape in self.shapes.iter_mut() {
+            if let Shape::Triangle(..) = shape {
+                shape.for_each_vertex_mut(|index| {
+                    let space = corner_spaces.next();
+                    index.tangents.set(tangent_slot, space);
+                    index.binormals.set(binormal_slot, space);
+                });
+            }
+            else {
+                // Other shapes have no generated tangents, drop any stale indices into the replaced values
+                shape.for_each_vertex_mut(|index| {
+                    index.tangents.set(tangent_slot, None);
+                    index.binormals.set(binormal_slot, None);
+                });
+            }
+        }
+
+        Ok(())
+    }
+
+    // Tangent of the corner with the handedness of its binormal in w, as expected by normal mapping shaders
+    pub fn tangent_with_sign(&self, index: &PTNCIndex, set: usize) -> Option<Vector4> {
+        let tangent_slot = self.tangent_slot(set)?;
+        let binormal_slot = self.binormal_slot(set)?;
+
+        let tangent = *self.tangents[tangent_slot].values.get(index.tangents.get(tangent_slot)?)?;
+        let binormal = *self.binormals[binormal_slot].values.get(index.binormals.get(binormal_slot)?)?;
+        let normal = *self.normals.get(index.normal?)?;
+
+        let w = if normal.cross(tangent).dot(binormal) < 0.0 { -1.0 } else { 1.0 };
+
+        Some(Vector4 { x: tangent.x, y: tangent.y, z: tangent.z, w })
+    }
+}
@@ -0,0 +1,372 @@
+use math::{Vector3, EPSILON};
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+struct Point {
+    x: f32,
+    y: f32,
+}
+
+// Twice the signed area of the triangle, positive when counter clockwise
+fn orient(a: Point, b: Point, c: Point) -> f32 {
+    (b.x - a.x) * (c.y - a.y) - (b.y - a.y) * (c.x - a.x)
+}
+
+fn signed_area(ring: &[usize], points: &[Point]) -> f32 {
+    let mut area = 0.0;
+    for i in 0..ring.len() {
+        let a = points[ring[i]];
+        let b = points[ring[(i + 1) % ring.len()]];
+        area += a.x * b.y - b.x * a.y;
+    }
+
+    area * 0.5
+}
+
+// Normal of the plane that best fits the ring using Newell's method
+// Normal of the plane that best fits the ring, with a length of twice the ring area divided by
+// the squared size of the ring so small rings are not mistaken for degenerate ones
+fn newell_normal(ring: &[Vector3]) -> Vector3 {
+    let mut normal = Vector3 { x: 0.0, y: 0.0, z: 0.0 };
+    let origin = ring[0];
+    let size = ring.iter().map(|point| (*point - origin).length()).fold(0.0, f32::max);
+    if size == 0.0 {
+        return normal;
+    }
+
+    let scale = 1.0 / size;
+    for i in 0..ring.len() {
+        let a = (ring[i] - origin) * scale;
+        let b = (ring[(i + 1) % ring.len()] - origin) * scale;
+        normal.x += (a.y - b.y) * (a.z + b.z);
+        normal.y += (a.z - b.z) * (a.x + b.x);
+        normal.z += (a.x - b.x) * (a.y + b.y);
+    }
+
+    normal
+}
+
+// Twice the area is tiny compared to the squared longest edge, so the triangle is a sliver
+fn is_sliver(a: Point, b: Point, c: Point) -> bool {
+    let length_squared = |p: Point, q: Point| (q.x - p.x) * (q.x - p.x) + (q.y - p.y) * (q.y - p.y);
+    let longest = length_squared(a, b).max(length_squared(b, c)).max(length_squared(c, a));
+    orient(a, b, c).abs() <= EPSILON * longest
+}
+
+fn segments_intersect(a: Point, b: Point, c: Point, d: Point) -> bool {
+    let d1 = orient(c, d, a);
+    let d2 = orient(c, d, b);
+    let d3 = orient(a, b, c);
+    let d4 = orient(a, b, d);
+
+    ((d1 > 0.0 && d2 < 0.0) || (d1 < 0.0 && d2 > 0.0))
+    && ((d3 > 0.0 && d4 < 0.0) || (d3 < 0.0 && d4 > 0.0))
+}
+
+fn point_in_triangle(p: Point, a: Point, b: Point, c: Point) -> bool {
+    orient(a, b, p) >= 0.0 && orient(b, c, p) >= 0.0 && orient(c, a, p) >= 0.0
+}
+
+// Whether the direction from the vertex at `index` towards `target` lies inside the polygon
+// at that vertex. Needed to pick the right copy of a vertex duplicated by an earlier bridge
+fn in_vertex_wedge(polygon: &[usize], index: usize, target: Point, points: &[Point]) -> bool {
+    let len = polygon.len();
+    let prev = points[polygon[(index + len - 1) % len]];
+    let current = points[polygon[index]];
+    let next = points[polygon[(index + 1) % len]];
+
+    if orient(prev, current, next) >= 0.0 {
+        orient(current, next, target) > 0.0 && orient(prev, current, target) > 0.0
+    }
+    else {
+        !(orient(current, next, target) <= 0.0 && orient(prev, current, target) <= 0.0)
+    }
+}
+
+fn edges_blocked(from: Point, to: Point, ring: &[usize], points: &[Point]) -> bool {
+    for i in 0..ring.len() {
+        let a = points[ring[i]];
+        let b = points[ring[(i + 1) % ring.len()]];
+        if a == from || a == to || b == from || b == to {
+            continue;
+        }
+        if segments_intersect(from, to, a, b) {
+            return true;
+        }
+    }
+
+    false
+}
+
+// Joins a hole into the polygon with a pair of coincident edges from its right most vertex
+// to the closest visible polygon vertex
+fn bridge_hole(polygon: &mut Vec<usize>, hole: &[usize], other_holes: &[Vec<usize>], points: &[Point]) {
+    let mut hole_start = 0;
+    for (i, index) in hole.iter().enumerate() {
+        if points[*index].x > points[hole[hole_start]].x {
+            hole_start = i;
+        }
+    }
+    let hole_point = points[hole[hole_start]];
+
+    let mut candidates: Vec<usize> = (0..polygon.len()).collect();
+    candidates.sort_by(|a, b| {
+        let a = points[polygon[*a]];
+        let b = points[polygon[*b]];
+        let da = (a.x - hole_point.x).powi(2) + (a.y - hole_point.y).powi(2);
+        let db = (b.x - hole_point.x).powi(2) + (b.y - hole_point.y).powi(2);
+        da.partial_cmp(&db).unwrap_or(::std::cmp::Ordering::Equal)
+    });
+
+    let visible = candidates.iter().cloned().find(|candidate| {
+        let point = points[polygon[*candidate]];
+        in_vertex_wedge(polygon, *candidate, hole_point, points)
+        && !edges_blocked(hole_point, point, polygon, points)
+        && !edges_blocked(hole_point, point, hole, points)
+        && !other_holes.iter().any(|other| edges_blocked(hole_point, point, other, points))
+    });
+
+    // Fall back to the closest vertex if numerical issues hide every candidate
+    let bridge = match visible.or_else(|| candidates.first().cloned()) {
+        Some(bridge) => bridge,
+        None => return,
+    };
+
+    let mut merged = Vec::with_capacity(polygon.len() + hole.len() + 2);
+    merged.extend_from_slice(&polygon[..=bridge]);
+    for i in 0..=hole.len() {
+        merged.push(hole[(hole_start + i) % hole.len()]);
+    }
+    merged.extend_from_slice(&polygon[bridge..]);
+
+    *polygon = merged;
+}
+
+fn is_ear(polygon: &[usize], prev: usize, current: usize, next: usize, points: &[Point]) -> bool {
+    let a = points[polygon[prev]];
+    let b = points[polygon[current]];
+    let c = points[polygon[next]];
+
+    if orient(a, b, c) <= 0.0 {
+        return false;
+    }
+
+    for index in polygon.iter() {
+        let p = points[*index];
+        if p == a || p == b || p == c {
+            continue;
+        }
+        if point_in_triangle(p, a, b, c) {
+            return false;
+        }
+    }
+
+    true
+}
+
+fn ear_clip(mut polygon: Vec<usize>, points: &[Point]) -> Vec<[usize; 3]> {
+    let mut triangles = vec![];
+
+    while polygon.len() > 3 {
+        let len = polygon.len();
+        let mut ear = None;
+
+        for i in 0..len {
+            if is_ear(&polygon, (i + len - 1) % len, i, (i + 1) % len, points) {
+                ear = Some(i);
+                break;
+            }
+        }
+
+        // Degenerate input can leave no valid ear, clip a convex or collinear vertex instead
+        // so the loop always terminates
+        let ear = ear.unwrap_or_else(|| {
+            (0..len).find(|i| {
+                let a = points[polygon[(i + len - 1) % len]];
+                let b = points[polygon[*i]];
+                let c = points[polygon[(i + 1) % len]];
+                orient(a, b, c) >= 0.0
+            }).unwrap_or(0)
+        });
+
+        let prev = polygon[(ear + len - 1) % len];
+        let next = polygon[(ear + 1) % len];
+        let a = points[prev];
+        let b = points[polygon[ear]];
+        let c = points[next];
+        if !is_sliver(a, b, c) {
+            triangles.push([prev, polygon[ear], next]);
+        }
+        polygon.remove(ear);
+    }
+
+    if polygon.len() == 3 {
+        let [a, b, c] = [points[polygon[0]], points[polygon[1]], points[polygon[2]]];
+        if !is_sliver(a, b, c) {
+            triangles.push([polygon[0], polygon[1], polygon[2]]);
+        }
+    }
+
+    triangles
+}
+
+// Triangulates a planar polygon by ear clipping in the plane that best fits its outer ring.
+// Vertices are numbered with the outer ring first followed by each hole in order, and the
+// returned triangles keep the winding of the outer ring
+pub fn triangulate_polygon(outer: &[Vector3], holes: &[Vec<Vector3>]) -> Vec<[usize; 3]> {
+    if outer.len() < 3 {
+        return vec![];
+    }
+
+    let normal = match newell_normal(outer).normalize() {
+        Some(normal) => normal,
+        None => return (1..outer.len() - 1).map(|i| [0, i, i + 1]).collect(),
+    };
+
+    // Build an orthonormal basis (u, v) of the plane with u x v = normal
+    let helper = match normal.x.abs() < 0.9 {
+        true => Vector3 { x: 1.0, y: 0.0, z: 0.0 },
+        false => Vector3 { x: 0.0, y: 1.0, z: 0.0 },
+    };
+    let u = helper.cross(normal).normalize().unwrap();
+    let v = normal.cross(u);
+
+    let origin = outer[0];
+    let mut points = vec![];
+    for position in outer.iter().chain(holes.iter().flat_map(|hole| hole.iter())) {
+        let relative = *position - origin;
+        points.push(Point { x: relative.dot(u), y: relative.dot(v) });
+    }
+
+    let mut polygon: Vec<usize> = (0..outer.len()).collect();
+    if signed_area(&polygon, &points) < 0.0 {
+        polygon.reverse();
+    }
+
+    let mut start = outer.len();
+    let mut rings = vec![];
+    for hole in holes.iter() {
+        let mut ring: Vec<usize> = (start..start + hole.len()).collect();
+        start += hole.len();
+
+        if ring.len() < 3 {
+            continue;
+        }
+        // Holes wind opposite to the outer ring
+        if signed_area(&ring, &points) > 0.0 {
+            ring.reverse();
+        }
+        rings.push(ring);
+    }
+
+    // Bridge the holes from right to left so earlier bridges never block later ones
+    rings.sort_by(|a, b| {
+        let max_a = a.iter().map(|i| points[*i].x).fold(f32::MIN, f32::max);
+        let max_b = b.iter().map(|i| points[*i].x).fold(f32::MIN, f32::max);
+        max_b.partial_cmp(&max_a).unwrap_or(::std::cmp::Ordering::Equal)
+    });
+    for i in 0..rings.len() {
+        bridge_hole(&mut polygon, &rings[i], &rings[i + 1..], &points);
+    }
+
+    ear_clip(polygon, &points)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ring(points: &[(f32, f32)]) -> Vec<Vector3> {
+        points.iter().map(|&(x, y)| Vector3 { x, y, z: 0.0 }).collect()
+    }
+
+    // Signed areas of the triangles in the xy plane
+    fn areas(outer: &[Vector3], holes: &[Vec<Vector3>], triangles: &[[usize; 3]]) -> Vec<f32> {
+        let points: Vec<Vector3> = outer.iter().chain(holes.iter().flat_map(|hole| hole.iter())).cloned().collect();
+
+        triangles.iter().map(|&[a, b, c]| {
+            let (a, b, c) = (points[a], points[b], points[c]);
+            0.5 * ((b.x - a.x) * (c.y - a.y) - (b.y - a.y) * (c.x - a.x))
+        }).collect()
+    }
+
+    fn assert_area(outer: &[Vector3], holes: &[Vec<Vector3>], expected: f32) -> Vec<[usize; 3]> {
+        let triangles = triangulate_polygon(outer, holes);
+        let areas = areas(outer, holes, &triangles);
+
+        assert!((areas.iter().sum::<f32>() - expected).abs() < 1e-4, "area {:?} != {}", areas, expected);
+        // Every triangle keeps the winding of the outer ring
+        assert!(areas.iter().all(|area| area * expected > 0.0), "mixed windings {:?}", areas);
+        triangles
+    }
+
+    fn square(min: f32, max: f32) -> Vec<(f32, f32)> {
+        vec![(min, min), (max, min), (max, max), (min, max)]
+    }
+
+    #[test]
+    fn concave() {
+        let outer = ring(&[(0.0, 0.0), (2.0, 0.0), (2.0, 1.0), (1.0, 1.0), (1.0, 2.0), (0.0, 2.0)]);
+        assert_eq!(assert_area(&outer, &[], 3.0).len(), 4);
+
+        let comb = ring(&[
+            (0.0, 0.0), (5.0, 0.0), (5.0, 3.0), (4.0, 3.0), (4.0, 1.0), (3.0, 1.0),
+            (3.0, 3.0), (2.0, 3.0), (2.0, 1.0), (1.0, 1.0), (1.0, 3.0), (0.0, 3.0),
+        ]);
+        assert_area(&comb, &[], 11.0);
+    }
+
+    #[test]
+    fn one_hole() {
+        let outer = ring(&square(0.0, 4.0));
+        let hole = ring(&square(1.0, 3.0));
+        assert_eq!(assert_area(&outer, &[hole], 12.0).len(), 8);
+    }
+
+    #[test]
+    fn two_holes() {
+        let outer = ring(&square(0.0, 4.0));
+        let mut first = square(0.5, 1.5);
+        // Opposite winding to the other hole, holes are reoriented
+        first.reverse();
+        let holes = vec![ring(&first), ring(&square(2.5, 3.5))];
+        assert_area(&outer, &holes, 14.0);
+    }
+
+    #[test]
+    fn clockwise() {
+        let mut points = vec![(0.0, 0.0), (2.0, 0.0), (2.0, 1.0), (1.0, 1.0), (1.0, 2.0), (0.0, 2.0)];
+        points.reverse();
+        assert_area(&ring(&points), &[], -3.0);
+
+        let mut outer = square(0.0, 4.0);
+        outer.reverse();
+        assert_area(&ring(&outer), &[ring(&square(1.0, 3.0))], -12.0);
+    }
+
+    #[test]
+    fn degenerate() {
+        // Collinear vertices on the edges add no zero area triangles
+        let outer = ring(&[(0.0, 0.0), (1.0, 0.0), (2.0, 0.0), (2.0, 2.0), (1.0, 2.0), (0.0, 2.0)]);
+        assert_area(&outer, &[], 4.0);
+
+        // A ring without area still gives triangles for every vertex instead of failing
+        let line = ring(&[(0.0, 0.0), (1.0, 1.0), (2.0, 2.0), (3.0, 3.0)]);
+        let triangles = triangulate_polygon(&line, &[]);
+        assert_eq!(triangles.len(), 2);
+        assert!(areas(&line, &[], &triangles).iter().all(|area| area.abs() < 1e-6));
+
+        assert!(triangulate_polygon(&ring(&[(0.0, 0.0), (1.0, 0.0)]), &[]).is_empty());
+    }
+
+    #[test]
+    fn small_scale() {
+        // A millimeter sized polygon in meters is triangulated like a large one
+        let points: Vec<(f32, f32)> = [(0.0, 0.0), (2.0, 0.0), (2.0, 1.0), (1.0, 1.0), (1.0, 2.0), (0.0, 2.0)]
+            .iter().map(|&(x, y)| (x * 1e-3, y * 1e-3)).collect();
+        let outer = ring(&points);
+        let triangles = triangulate_polygon(&outer, &[]);
+        assert_eq!(triangles.len(), 4);
+        let area: f32 = areas(&outer, &[], &triangles).iter().sum();
+        assert!((area - 3e-6).abs() < 1e-9);
+    }
+}
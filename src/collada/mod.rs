@@ -7,6 +7,7 @@ pub mod animation;
 pub mod skeleton;
 pub mod document;
 pub mod util;
+pub mod transform;
 
 pub use self::animation::Animation;
 pub use self::mesh::{GenericMesh, Vertex, Shape};
@@ -17,7 +18,73 @@ pub use self::document::{*, controller::*, geometry::*, visual_scene::*};
 use math::Vector3;
 
 pub type Mesh = GenericMesh<Vector3>;
-// Pos Texture Normal Color
-pub type PTNCIndex = (usize, Option<usize>, Option<usize>, Option<usize>);
+// Pos Texture Normal
 pub type PTNIndex = (usize, Option<usize>, Option<usize>);
 
+// Indices into the sets of one semantic at a corner, by slot. Any number of sets is allowed,
+// trailing empty slots are not stored so corners with the same indices compare equal
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct SetIndices {
+    indices: Vec<Option<usize>>,
+}
+
+impl SetIndices {
+    pub fn get(&self, slot: usize) -> Option<usize> {
+        self.indices.get(slot).copied().flatten()
+    }
+
+    pub fn set(&mut self, slot: usize, index: Option<usize>) {
+        if slot >= self.indices.len() {
+            if index.is_none() {
+                return;
+            }
+            self.indices.resize(slot + 1, None);
+        }
+        self.indices[slot] = index;
+
+        while self.indices.last() == Some(&None) {
+            self.indices.pop();
+        }
+    }
+
+    // Makes room for a new empty slot, moving the indices of the slot and every later slot up by one
+    pub fn insert_slot(&mut self, slot: usize) {
+        if slot < self.indices.len() {
+            self.indices.insert(slot, None);
+        }
+    }
+}
+
+// Pos Texture Normal Color, plus texture space tangents and binormals.
+// Attributes with sets are indexed by the slot of their set
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct PTNCIndex {
+    pub position: usize,
+    pub tex_coords: SetIndices,
+    pub normal: Option<usize>,
+    pub colors: SetIndices,
+    pub tangents: SetIndices,
+    pub binormals: SetIndices,
+}
+
+impl PTNCIndex {
+    pub fn new(position: usize) -> PTNCIndex {
+        PTNCIndex {
+            position,
+            tex_coords: SetIndices::default(),
+            normal: None,
+            colors: SetIndices::default(),
+            tangents: SetIndices::default(),
+            binormals: SetIndices::default(),
+        }
+    }
+
+    pub fn tex_coord(&self) -> Option<usize> {
+        self.tex_coords.get(0)
+    }
+
+    pub fn color(&self) -> Option<usize> {
+        self.colors.get(0)
+    }
+}
+
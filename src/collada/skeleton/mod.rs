@@ -1,4 +1,5 @@
-use collada::error::*;
+use collada::{error::*, document::visual_scene::Node};
+use math::Matrix4;
 use xml_tree::*;
 use self::node::*;
 use std::error::Error;
@@ -60,6 +61,42 @@ impl Skeleton {
         Ok(index.unwrap())
     }
 
+    fn add_node(&mut self, node: &Node, parent: Option<usize>, helpers: Matrix4) -> Result<usize, SkeletonParseError> {
+        let index = self.next_index();
+        let mut skeleton_node = SkeletonNode::from_node(node, parent)?;
+        skeleton_node.default_trans = helpers * node.matrix;
+        self.nodes.push(skeleton_node);
+        self.add_children(node, index, Matrix4::identity())?;
+
+        Ok(index)
+    }
+
+    // Meshes and helpers parented to a bone are not part of the skeleton, but the joints below
+    // them are. They become children of the closest joint above, with the transforms of the
+    // skipped nodes folded into theirs
+    fn add_children(&mut self, node: &Node, parent: usize, helpers: Matrix4) -> Result<(), SkeletonParseError> {
+        for child in node.children.iter() {
+            if child.is_joint() {
+                let child_index = self.add_node(child, Some(parent), helpers)?;
+                self.nodes[parent].children.push(child_index);
+            }
+            else {
+                self.add_children(child, parent, helpers * child.matrix)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    // Builds the skeleton from a joint of a visual scene and every joint below it
+    pub fn from_node(node: &Node) -> Result<Skeleton, SkeletonParseError> {
+        let id = node.id.as_ref().ok_or(SkeletonParseError)?;
+        let mut skeleton = Skeleton { id: id.clone(), nodes: vec![] };
+        skeleton.add_node(node, None, Matrix4::identity())?;
+
+        Ok(skeleton)
+    }
+
     pub fn parse_skeleton(node: &XmlNode, tree: &XmlTree) -> Result<Skeleton, Box<dyn Error>> {
         if node.name.local_name != "node" {
             return Err(Box::new(SkeletonParseError));
@@ -1,6 +1,6 @@
 use xml_tree::*;
 use math::Matrix4;
-use collada::{util::*, error::*};
+use collada::{util::*, error::*, document::visual_scene::Node};
 use std::error::Error;
 
 
@@ -18,6 +18,16 @@ impl SkeletonNode {
         self.children.as_slice()
     }
 
+    pub fn from_node(node: &Node, parent: Option<usize>) -> Result<SkeletonNode, SkeletonParseError> {
+        Ok(SkeletonNode {
+            id: node.id.clone().ok_or(SkeletonParseError)?,
+            sid: node.sid.clone().ok_or(SkeletonParseError)?,
+            parent,
+            default_trans: node.matrix,
+            children: vec![],
+        })
+    }
+
     pub fn parse_node(node: &XmlNode, tree: &XmlTree, parent: Option<usize>) -> Result<SkeletonNode, Box<dyn Error>> {
         let id = node.get_attribute_with_name("id").ok_or(MissingAttributeError { attribute_name: String::from("id") })?;
         let sid = node.get_attribute_with_name("sid").ok_or(MissingAttributeError { attribute_name: String::from("id") })?;
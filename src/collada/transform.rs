@@ -0,0 +1,271 @@
+use collada::{util::*, error::*};
+use xml_tree::*;
+use math::{Matrix4, EPSILON};
+use std::error::Error;
+
+// One element of the transform stack of a node, angles are in degrees
+#[derive(Copy, Clone, Debug)]
+pub enum Transformation {
+    Matrix(Matrix4),
+    Translate([f32; 3]),
+    // Axis followed by the angle
+    Rotate([f32; 4]),
+    Scale([f32; 3]),
+    // Eye position, interest point and up direction. Places an object at the eye looking
+    // down its negative z axis at the interest point
+    LookAt([f32; 9]),
+    // Angle, rotation axis and translation axis. Moves points along the translation axis
+    // by the tangent of the angle times their distance along the rotation axis
+    Skew([f32; 7]),
+}
+
+impl Transformation {
+    pub fn matrix(&self) -> Matrix4 {
+        match *self {
+            Transformation::Matrix(matrix) => matrix,
+            Transformation::Translate(translation) => {
+                let mut matrix = Matrix4::identity();
+                matrix.set_translation(translation);
+                matrix
+            }
+            Transformation::Rotate([x, y, z, angle]) => rotation_matrix([x, y, z], angle),
+            Transformation::Scale(scale) => {
+                let mut matrix = Matrix4::identity();
+                matrix.scale(scale);
+                matrix
+            }
+            Transformation::LookAt(values) => look_at_matrix(&values),
+            Transformation::Skew(values) => skew_matrix(&values),
+        }
+    }
+
+    pub fn parse_transformation(node: &XmlNode) -> Result<Option<Transformation>, Box<dyn Error>> {
+        let values = |len: usize| -> Result<Vec<f32>, Box<dyn Error>> {
+            let characters = node.get_characters().ok_or(TransformationParseError)?;
+            let array: Vec<f32> = parse_array(characters)?;
+            match array.len() == len {
+                true => Ok(array),
+                false => Err(Box::new(TransformationParseError)),
+            }
+        };
+
+        let transformation = match node.name.local_name.as_ref() {
+            "matrix" => Transformation::Matrix(Matrix4::from_slice(values(16)?.as_slice())?),
+            "translate" => {
+                let array = values(3)?;
+                Transformation::Translate([array[0], array[1], array[2]])
+            }
+            "rotate" => {
+                let array = values(4)?;
+                Transformation::Rotate([array[0], array[1], array[2], array[3]])
+            }
+            "scale" => {
+                let array = values(3)?;
+                Transformation::Scale([array[0], array[1], array[2]])
+            }
+            "lookat" => {
+                let array = values(9)?;
+                let mut look_at = [0.0; 9];
+                look_at.copy_from_slice(&array);
+                Transformation::LookAt(look_at)
+            }
+            "skew" => {
+                let array = values(7)?;
+                let mut skew = [0.0; 7];
+                skew.copy_from_slice(&array);
+                Transformation::Skew(skew)
+            }
+            _ => return Ok(None),
+        };
+
+        Ok(Some(transformation))
+    }
+}
+
+// Rotation around the axis by the angle in degrees, counter clockwise when looking down the axis
+fn rotation_matrix(axis: [f32; 3], angle: f32) -> Matrix4 {
+    let length = (axis[0] * axis[0] + axis[1] * axis[1] + axis[2] * axis[2]).sqrt();
+    if length == 0.0 {
+        return Matrix4::identity();
+    }
+    let [x, y, z] = [axis[0] / length, axis[1] / length, axis[2] / length];
+    let (sin, cos) = angle.to_radians().sin_cos();
+    let t = 1.0 - cos;
+
+    let mut matrix = Matrix4::identity();
+    matrix.values[0] = t * x * x + cos;
+    matrix.values[1] = t * x * y - sin * z;
+    matrix.values[2] = t * x * z + sin * y;
+    matrix.values[4] = t * x * y + sin * z;
+    matrix.values[5] = t * y * y + cos;
+    matrix.values[6] = t * y * z - sin * x;
+    matrix.values[8] = t * x * z - sin * y;
+    matrix.values[9] = t * y * z + sin * x;
+    matrix.values[10] = t * z * z + cos;
+
+    matrix
+}
+
+fn cross(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    [a[1] * b[2] - a[2] * b[1], a[2] * b[0] - a[0] * b[2], a[0] * b[1] - a[1] * b[0]]
+}
+
+fn normalize(vector: [f32; 3]) -> Option<[f32; 3]> {
+    let length = (vector[0] * vector[0] + vector[1] * vector[1] + vector[2] * vector[2]).sqrt();
+    match length > 0.0 {
+        true => Some([vector[0] / length, vector[1] / length, vector[2] / length]),
+        false => None,
+    }
+}
+
+// Inverse of the view matrix of the eye, so the z axis points from the interest point to the eye
+fn look_at_matrix(values: &[f32; 9]) -> Matrix4 {
+    let eye = [values[0], values[1], values[2]];
+    let interest = [values[3], values[4], values[5]];
+    let up = [values[6], values[7], values[8]];
+
+    let z = normalize([eye[0] - interest[0], eye[1] - interest[1], eye[2] - interest[2]]);
+    let x = z.and_then(|z| normalize(cross(up, z)));
+    let (x, z) = match (x, z) {
+        (Some(x), Some(z)) => (x, z),
+        // The eye is at the interest point or looks along the up direction
+        _ => {
+            let mut matrix = Matrix4::identity();
+            matrix.set_translation(eye);
+            return matrix;
+        }
+    };
+    let y = cross(z, x);
+
+    let mut matrix = Matrix4::identity();
+    matrix.set_column(0, [x[0], x[1], x[2], 0.0]);
+    matrix.set_column(1, [y[0], y[1], y[2], 0.0]);
+    matrix.set_column(2, [z[0], z[1], z[2], 0.0]);
+    matrix.set_column(3, [eye[0], eye[1], eye[2], 1.0]);
+
+    matrix
+}
+
+fn dot(a: [f32; 3], b: [f32; 3]) -> f32 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+// RenderMan's Skew as computed in the RenderMan Companion, points move along the translation
+// axis d2 by s times their component along d1o, the part of the rotation axis d1 perpendicular
+// to d2. The shear is undefined when the angle plus the angle between d1o and d1 reaches
+// 90 degrees, or the axes are parallel, and the identity is used instead
+fn skew_matrix(values: &[f32; 7]) -> Matrix4 {
+    let axes = normalize([values[1], values[2], values[3]])
+        .and_then(|d1| normalize([values[4], values[5], values[6]]).map(|d2| (d1, d2)));
+    let (d1, d2) = match axes {
+        Some(axes) => axes,
+        None => return Matrix4::identity(),
+    };
+
+    let par = dot(d1, d2);
+    let d1o = match normalize([d1[0] - par * d2[0], d1[1] - par * d2[1], d1[2] - par * d2[2]]) {
+        Some(d1o) => d1o,
+        None => return Matrix4::identity(),
+    };
+    let perp = dot(d1, d1o);
+
+    let angle = values[0].to_radians() + perp.clamp(-1.0, 1.0).acos();
+    if angle.cos() <= EPSILON {
+        return Matrix4::identity();
+    }
+    let s = angle.tan() * perp - par;
+
+    let mut matrix = Matrix4::identity();
+    for (row, d2) in d2.iter().enumerate() {
+        for (column, d1o) in d1o.iter().enumerate() {
+            matrix.values[row * 4 + column] += s * d2 * d1o;
+        }
+    }
+
+    matrix
+}
+
+// Transform element with the sid animations use to target it
+#[derive(Clone, Debug)]
+pub struct Transform {
+    pub sid: Option<String>,
+    pub transformation: Transformation,
+}
+
+impl Transform {
+    pub fn matrix(&self) -> Matrix4 {
+        self.transformation.matrix()
+    }
+}
+
+// Transform elements of a node in document order
+pub fn parse_transforms(node: &XmlNode, tree: &XmlTree) -> Result<Vec<Transform>, Box<dyn Error>> {
+    let mut transforms = vec![];
+
+    for child in tree.nodes_iter(node.get_children()) {
+        let child = child.unwrap();
+
+        if let Some(transformation) = Transformation::parse_transformation(child)? {
+            let sid = child.get_attribute_with_name("sid").map(|sid| sid.to_string());
+            transforms.push(Transform { sid, transformation });
+        }
+    }
+
+    Ok(transforms)
+}
+
+// Multiplies the transforms in document order, the last element is applied to points first
+pub fn compose(transforms: &[Transform]) -> Matrix4 {
+    transforms.iter().fold(Matrix4::identity(), |matrix, transform| matrix * transform.matrix())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use math::Vector3;
+
+    fn skew(values: [f32; 7], point: [f32; 3]) -> Vector3 {
+        let point = Vector3 { x: point[0], y: point[1], z: point[2] };
+        Transformation::Skew(values).matrix().transform_point(point)
+    }
+
+    fn assert_point(point: Vector3, expected: [f32; 3]) {
+        let actual = [point.x, point.y, point.z];
+        for (a, b) in actual.iter().zip(expected.iter()) {
+            assert!((a - b).abs() < 1e-5, "{:?} != {:?}", actual, expected);
+        }
+    }
+
+    #[test]
+    fn skew_perpendicular() {
+        // Points move along x by their y component
+        let values = [45.0, 0.0, 1.0, 0.0, 1.0, 0.0, 0.0];
+        assert_point(skew(values, [0.0, 2.0, 0.0]), [2.0, 2.0, 0.0]);
+        assert_point(skew(values, [1.0, 0.0, 3.0]), [1.0, 0.0, 3.0]);
+    }
+
+    #[test]
+    fn skew_non_perpendicular() {
+        // par = 1/2 and perp = sqrt(3)/2 so s = tan(60) * sqrt(3)/2 - 1/2 = 1, points move
+        // along (1, 0, 1) / sqrt(2) by their component along d1o = (1, 2, -1) / sqrt(6)
+        let values = [30.0, 1.0, 1.0, 0.0, 1.0, 0.0, 1.0];
+        let third = 1.0 / 3.0_f32.sqrt();
+        let twelfth = 1.0 / 12.0_f32.sqrt();
+        assert_point(skew(values, [0.0, 1.0, 0.0]), [third, 1.0, third]);
+        assert_point(skew(values, [1.0, 0.0, 0.0]), [1.0 + twelfth, 0.0, twelfth]);
+        assert_point(skew(values, [1.0, 0.0, 1.0]), [1.0, 0.0, 1.0]);
+
+        let matrix = Transformation::Skew(values).matrix();
+        assert!((matrix.determinant() - 1.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn skew_undefined() {
+        // The angle plus the 45 degrees between d1o and d1 is 90 degrees
+        assert_point(skew([45.0, 0.0, 2.0, 0.0, 1.0, 1.0, 0.0], [1.0, 2.0, 3.0]), [1.0, 2.0, 3.0]);
+        assert_point(skew([100.0, 0.0, 1.0, 0.0, 1.0, 0.0, 0.0], [1.0, 2.0, 3.0]), [1.0, 2.0, 3.0]);
+
+        // Parallel axes leave points in place
+        assert_point(skew([45.0, 0.0, 1.0, 0.0, 0.0, 3.0, 0.0], [1.0, 2.0, 3.0]), [1.0, 2.0, 3.0]);
+    }
+}
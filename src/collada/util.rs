@@ -1,5 +1,5 @@
 use math::Matrix4;
-use super::error::*;
+use super::{error::*, transform::*};
 use std::error::Error;
 use std::str::FromStr;
 use xml_tree::*;
@@ -16,56 +16,11 @@ pub fn parse_array<T: FromStr>(array: &str) -> Result<Vec<T>, ArrayError> {
     Ok(values)
 }
 
+// Composes the transform elements of the node in document order
 pub fn parse_transformation(node: &XmlNode, tree: &XmlTree) -> Result<Matrix4, Box<dyn Error>> {
-    let mut matrix = Matrix4::identity();
+    let transforms = parse_transforms(node, tree)?;
 
-    for child in tree.nodes_iter(node.get_children()) {
-        let child = child.unwrap();
-
-        match child.name.local_name.as_ref() {
-            "matrix" => {
-                let characters = child.get_characters().ok_or(TransformationParseError)?;
-                let array = parse_array(characters)?;
-                matrix = Matrix4::from_slice(array.as_slice())?;
-            },
-            "translate" => {
-                let characters = child.get_characters().ok_or(TransformationParseError)?;
-                let array = parse_array(characters)?;
-                if array.len() != 3 {
-                    return Err(Box::new(TransformationParseError));
-                }
-                let array = [array[0], array[1], array[2]];
-                matrix.set_translation(array);
-            },
-            "rotate" => {
-                let characters = child.get_characters().ok_or(TransformationParseError)?;
-                let array = parse_array(characters)?;
-                if array.len() != 4 {
-                    return Err(Box::new(TransformationParseError));
-                }
-                let array = [array[0], array[1], array[2], array[3]];
-                let target = child.get_attribute_with_name("sid").ok_or(TransformationParseError)?;
-                match &target[8..] {
-                    "X" => matrix.set_column(0, array),
-                    "Y" => matrix.set_column(1, array),
-                    "Z" => matrix.set_column(2, array),
-                    _ => {}
-                }
-            },
-            "scale" => {
-                let characters = child.get_characters().ok_or(TransformationParseError)?;
-                let array = parse_array(characters)?;
-                if array.len() != 3 {
-                    return Err(Box::new(TransformationParseError));
-                }
-                let array = [array[0], array[1], array[2]];
-                matrix.scale(array);
-            }
-            _ => {}
-        }
-    }
-
-    Ok(matrix)
+    Ok(compose(&transforms))
 }
 
 #[derive(Debug)]
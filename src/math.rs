@@ -1,6 +1,7 @@
-use collada::mesh::Vertex;
+use collada::mesh::{Vertex, Position};
 use std::error::Error;
 use std::fmt::{self, Display, Formatter};
+use std::ops::{Add, Sub, Mul, Neg};
 
 #[derive(Copy, Clone, Debug)]
 pub struct Matrix4CreationError {
@@ -15,6 +16,10 @@ impl Display for Matrix4CreationError {
 
 impl Error for Matrix4CreationError {}
 
+// Lengths at or below this are treated as zero, also the relative tolerance of
+// determinants and areas compared to the size of what they are computed from
+pub const EPSILON: f32 = 1e-6;
+
 #[derive(Debug, Clone, Copy)]
 pub struct Vector3 {
     pub x: f32,
@@ -68,14 +73,211 @@ impl Vertex for Vector3 {
     }
 }
 
+impl Position for Vector3 {
+    fn position(&self) -> Vector3 {
+        *self
+    }
+}
+
+impl Vector3 {
+    pub fn dot(self, other: Vector3) -> f32 {
+        self.x * other.x + self.y * other.y + self.z * other.z
+    }
+
+    pub fn cross(self, other: Vector3) -> Vector3 {
+        Vector3 {
+            x: self.y * other.z - self.z * other.y,
+            y: self.z * other.x - self.x * other.z,
+            z: self.x * other.y - self.y * other.x,
+        }
+    }
+
+    pub fn length(self) -> f32 {
+        self.dot(self).sqrt()
+    }
+
+    // None for vectors too short to have a direction
+    pub fn normalize(self) -> Option<Vector3> {
+        let length = self.length();
+        if length <= EPSILON {
+            return None;
+        }
+
+        Some(self * (1.0 / length))
+    }
+
+    pub fn lerp(self, other: Vector3, t: f32) -> Vector3 {
+        self + (other - self) * t
+    }
+}
+
+impl Add for Vector3 {
+    type Output = Vector3;
+
+    fn add(self, other: Vector3) -> Vector3 {
+        Vector3 { x: self.x + other.x, y: self.y + other.y, z: self.z + other.z }
+    }
+}
+
+impl Sub for Vector3 {
+    type Output = Vector3;
+
+    fn sub(self, other: Vector3) -> Vector3 {
+        Vector3 { x: self.x - other.x, y: self.y - other.y, z: self.z - other.z }
+    }
+}
+
+impl Mul<f32> for Vector3 {
+    type Output = Vector3;
+
+    fn mul(self, scale: f32) -> Vector3 {
+        Vector3 { x: self.x * scale, y: self.y * scale, z: self.z * scale }
+    }
+}
+
+impl Neg for Vector3 {
+    type Output = Vector3;
+
+    fn neg(self) -> Vector3 {
+        Vector3 { x: -self.x, y: -self.y, z: -self.z }
+    }
+}
+
 #[derive(Debug, Clone, Copy)]
 pub struct Vector2 {
     pub x: f32,
     pub y: f32,
 }
 
+impl Vector2 {
+    pub fn dot(self, other: Vector2) -> f32 {
+        self.x * other.x + self.y * other.y
+    }
+
+    // Z component of the cross product of the vectors extended to 3D
+    pub fn cross(self, other: Vector2) -> f32 {
+        self.x * other.y - self.y * other.x
+    }
+
+    pub fn length(self) -> f32 {
+        self.dot(self).sqrt()
+    }
+
+    pub fn normalize(self) -> Option<Vector2> {
+        let length = self.length();
+        if length <= EPSILON {
+            return None;
+        }
+
+        Some(self * (1.0 / length))
+    }
+
+    pub fn lerp(self, other: Vector2, t: f32) -> Vector2 {
+        self + (other - self) * t
+    }
+}
+
+impl Add for Vector2 {
+    type Output = Vector2;
+
+    fn add(self, other: Vector2) -> Vector2 {
+        Vector2 { x: self.x + other.x, y: self.y + other.y }
+    }
+}
+
+impl Sub for Vector2 {
+    type Output = Vector2;
+
+    fn sub(self, other: Vector2) -> Vector2 {
+        Vector2 { x: self.x - other.x, y: self.y - other.y }
+    }
+}
+
+impl Mul<f32> for Vector2 {
+    type Output = Vector2;
+
+    fn mul(self, scale: f32) -> Vector2 {
+        Vector2 { x: self.x * scale, y: self.y * scale }
+    }
+}
+
+impl Neg for Vector2 {
+    type Output = Vector2;
+
+    fn neg(self) -> Vector2 {
+        Vector2 { x: -self.x, y: -self.y }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Vector4 {
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+    pub w: f32,
+}
+
+impl Vector4 {
+    pub fn dot(self, other: Vector4) -> f32 {
+        self.x * other.x + self.y * other.y + self.z * other.z + self.w * other.w
+    }
+
+    pub fn length(self) -> f32 {
+        self.dot(self).sqrt()
+    }
+
+    pub fn normalize(self) -> Option<Vector4> {
+        let length = self.length();
+        if length <= EPSILON {
+            return None;
+        }
+
+        Some(self * (1.0 / length))
+    }
+
+    pub fn lerp(self, other: Vector4, t: f32) -> Vector4 {
+        self + (other - self) * t
+    }
+}
+
+impl Add for Vector4 {
+    type Output = Vector4;
+
+    fn add(self, other: Vector4) -> Vector4 {
+        Vector4 { x: self.x + other.x, y: self.y + other.y, z: self.z + other.z, w: self.w + other.w }
+    }
+}
+
+impl Sub for Vector4 {
+    type Output = Vector4;
+
+    fn sub(self, other: Vector4) -> Vector4 {
+        Vector4 { x: self.x - other.x, y: self.y - other.y, z: self.z - other.z, w: self.w - other.w }
+    }
+}
+
+impl Mul<f32> for Vector4 {
+    type Output = Vector4;
+
+    fn mul(self, scale: f32) -> Vector4 {
+        Vector4 { x: self.x * scale, y: self.y * scale, z: self.z * scale, w: self.w * scale }
+    }
+}
+
+impl Neg for Vector4 {
+    type Output = Vector4;
+
+    fn neg(self) -> Vector4 {
+        Vector4 { x: -self.x, y: -self.y, z: -self.z, w: -self.w }
+    }
+}
+
+// Row major, values[row * 4 + column], which is the order matrices are written in COLLADA
+// documents and the order from_slice reads them in. Matrices transform column vectors so the
+// translation is the last column, at values 3, 7 and 11. get_row, get_column, set_column,
+// set_translation and scale all follow this layout. The values were always stored in document
+// order, older versions labelled them column major and had get_row and get_column swapped
 #[derive(Debug, Clone, Copy)]
-// Column major
 pub struct Matrix4 {
     pub values: [f32; 16],
 }
@@ -126,7 +328,7 @@ impl Matrix4 {
     pub fn print_mat(&self) {
         for i in 0..4 {
             for j in 0..4 {
-                let index = j * 4 + i;
+                let index = i * 4 + j;
                 print!("{}  ", self.values[index]);
             }
             println!();
@@ -135,47 +337,526 @@ impl Matrix4 {
     
     pub fn get_column(&self, index: usize) -> [f32; 4] {
         assert!(index < 4);
-        let column = index * 4;
         [
-            self.values[column],
-            self.values[column + 1],
-            self.values[column + 2],
-            self.values[column + 3]
+            self.values[index],
+            self.values[index + 4],
+            self.values[index + 8],
+            self.values[index + 12]
         ]
     }
     
     pub fn get_row(&self, index: usize) -> [f32; 4] {
         assert!(index < 4);
+        let row = index * 4;
         [
-            self.values[index],
-            self.values[index + 4],
-            self.values[index + 8],
-            self.values[index + 12]
+            self.values[row],
+            self.values[row + 1],
+            self.values[row + 2],
+            self.values[row + 3]
         ]
     }
     
     pub fn set_column(&mut self, column: usize, value: [f32; 4]) {
         assert!(column < 4);
-        let column = column * 4;
         self.values[column] = value[0];
-        self.values[column + 1] = value[1];
-        self.values[column + 2] = value[2];
-        self.values[column + 3] = value[3];
+        self.values[column + 4] = value[1];
+        self.values[column + 8] = value[2];
+        self.values[column + 12] = value[3];
     }
 
     pub fn set_translation(&mut self, trans: [f32; 3]) {
-        self.values[12] = trans[0];
-        self.values[13] = trans[1];
-        self.values[14] = trans[2];
+        self.values[3] = trans[0];
+        self.values[7] = trans[1];
+        self.values[11] = trans[2];
     }
 
+    // Scales the columns of the upper 3x3, the same as multiplying by a scale matrix on the right
     pub fn scale(&mut self, scale: [f32; 3]) {
         for i in 0..3 {
-            let start = i * 4;
-            for j in 0..3 {
-                let index = start + j;
-                self.values[index] *= scale[i]; 
+            for (j, scale) in scale.iter().enumerate() {
+                self.values[i * 4 + j] *= scale;
+            }
+        }
+    }
+
+    pub fn transpose_in_place(&mut self) {
+        for i in 0..4 {
+            for j in i + 1..4 {
+                self.values.swap(i * 4 + j, j * 4 + i);
+            }
+        }
+    }
+
+    pub fn determinant(&self) -> f32 {
+        let cofactors = self.cofactors();
+        (0..4).map(|j| self.values[j] * cofactors[j]).sum()
+    }
+
+    // None for singular matrices
+    pub fn inverse(&self) -> Option<Matrix4> {
+        let cofactors = self.cofactors();
+        let determinant: f32 = (0..4).map(|j| self.values[j] * cofactors[j]).sum();
+        // The determinant is compared with the largest one possible for rows or columns of
+        // these lengths, so the test does not depend on the scale of the matrix
+        let length = |values: [f32; 4]| values.iter().map(|value| value * value).sum::<f32>().sqrt();
+        let rows: f32 = (0..4).map(|i| length([self.values[i * 4], self.values[i * 4 + 1], self.values[i * 4 + 2], self.values[i * 4 + 3]])).product();
+        let columns: f32 = (0..4).map(|j| length([self.values[j], self.values[4 + j], self.values[8 + j], self.values[12 + j]])).product();
+        if determinant.abs() <= EPSILON * rows.min(columns) {
+            return None;
+        }
+
+        // The inverse is the transposed cofactor matrix divided by the determinant
+        let mut values = [0.0_f32; 16];
+        for i in 0..4 {
+            for j in 0..4 {
+                values[j * 4 + i] = cofactors[i * 4 + j] / determinant;
+            }
+        }
+
+        Some(Matrix4 { values })
+    }
+
+    // Signed minors of every element, in the same layout as the values
+    fn cofactors(&self) -> [f32; 16] {
+        let mut cofactors = [0.0_f32; 16];
+
+        for row in 0..4 {
+            for column in 0..4 {
+                let mut minor = [0.0_f32; 9];
+                let mut k = 0;
+                for i in (0..4).filter(|i| *i != row) {
+                    for j in (0..4).filter(|j| *j != column) {
+                        minor[k] = self.values[i * 4 + j];
+                        k += 1;
+                    }
+                }
+
+                let determinant = minor[0] * (minor[4] * minor[8] - minor[5] * minor[7])
+                    - minor[1] * (minor[3] * minor[8] - minor[5] * minor[6])
+                    + minor[2] * (minor[3] * minor[7] - minor[4] * minor[6]);
+                let sign = if (row + column) % 2 == 0 { 1.0 } else { -1.0 };
+                cofactors[row * 4 + column] = sign * determinant;
+            }
+        }
+
+        cofactors
+    }
+
+    pub fn transform_vector(&self, vector: Vector4) -> Vector4 {
+        let v = &self.values;
+
+        Vector4 {
+            x: v[0] * vector.x + v[1] * vector.y + v[2] * vector.z + v[3] * vector.w,
+            y: v[4] * vector.x + v[5] * vector.y + v[6] * vector.z + v[7] * vector.w,
+            z: v[8] * vector.x + v[9] * vector.y + v[10] * vector.z + v[11] * vector.w,
+            w: v[12] * vector.x + v[13] * vector.y + v[14] * vector.z + v[15] * vector.w,
+        }
+    }
+
+    // Applies the translation and divides by w for projective matrices
+    pub fn transform_point(&self, point: Vector3) -> Vector3 {
+        let result = self.transform_vector(Vector4 { x: point.x, y: point.y, z: point.z, w: 1.0 });
+        let w = if result.w != 0.0 { result.w } else { 1.0 };
+
+        Vector3 { x: result.x / w, y: result.y / w, z: result.z / w }
+    }
+
+    // Ignores the translation
+    pub fn transform_direction(&self, direction: Vector3) -> Vector3 {
+        let result = self.transform_vector(Vector4 { x: direction.x, y: direction.y, z: direction.z, w: 0.0 });
+
+        Vector3 { x: result.x, y: result.y, z: result.z }
+    }
+
+    // Scales, then rotates, then translates column vectors, T * R * S
+    pub fn from_trs(translation: Vector3, rotation: Quaternion, scale: Vector3) -> Matrix4 {
+        let mut matrix = rotation.to_matrix();
+        matrix.scale([scale.x, scale.y, scale.z]);
+        matrix.set_translation([translation.x, translation.y, translation.z]);
+
+        matrix
+    }
+
+    // Splits an affine matrix without shear into translation, rotation and scale so that
+    // from_trs gives the matrix back. Mirrored matrices get a negative x scale
+    pub fn decompose(&self) -> (Vector3, Quaternion, Vector3) {
+        let translation = Vector3 { x: self.values[3], y: self.values[7], z: self.values[11] };
+
+        let column = |index: usize| {
+            let column = self.get_column(index);
+            Vector3 { x: column[0], y: column[1], z: column[2] }
+        };
+        let axes = [column(0), column(1), column(2)];
+
+        let mut scale = Vector3 { x: axes[0].length(), y: axes[1].length(), z: axes[2].length() };
+        if axes[0].dot(axes[1].cross(axes[2])) < 0.0 {
+            scale.x = -scale.x;
+        }
+
+        let mut rotation = Matrix4::identity();
+        for (i, (axis, scale)) in axes.iter().zip([scale.x, scale.y, scale.z].iter()).enumerate() {
+            if scale.abs() > EPSILON {
+                let axis = *axis * (1.0 / scale);
+                rotation.set_column(i, [axis.x, axis.y, axis.z, 0.0]);
             }
         }
+
+        (translation, Quaternion::from_matrix(&rotation), scale)
+    }
+}
+
+// Multiplies the values in the order they are written in documents, row by column
+impl Mul for Matrix4 {
+    type Output = Matrix4;
+
+    fn mul(self, other: Matrix4) -> Matrix4 {
+        let mut values = [0.0_f32; 16];
+
+        for row in 0..4 {
+            for column in 0..4 {
+                values[row * 4 + column] = (0..4)
+                    .map(|i| self.values[row * 4 + i] * other.values[i * 4 + column])
+                    .sum();
+            }
+        }
+
+        Matrix4 { values }
+    }
+}
+
+// Unit quaternions are rotations, w is the scalar part
+#[derive(Debug, Clone, Copy)]
+pub struct Quaternion {
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+    pub w: f32,
+}
+
+impl Quaternion {
+    pub fn identity() -> Quaternion {
+        Quaternion { x: 0.0, y: 0.0, z: 0.0, w: 1.0 }
+    }
+
+    // Angle in radians, counterclockwise when looking against the axis
+    pub fn from_axis_angle(axis: Vector3, angle: f32) -> Quaternion {
+        let axis = match axis.normalize() {
+            Some(axis) => axis,
+            None => return Quaternion::identity(),
+        };
+        let (sin, cos) = (angle / 2.0).sin_cos();
+
+        Quaternion { x: axis.x * sin, y: axis.y * sin, z: axis.z * sin, w: cos }
+    }
+
+    // Rotation of the upper 3x3 of the matrix, which should have no scale
+    pub fn from_matrix(matrix: &Matrix4) -> Quaternion {
+        let m = |row: usize, column: usize| matrix.values[row * 4 + column];
+        let trace = m(0, 0) + m(1, 1) + m(2, 2);
+
+        // Divides by the largest of the four components to stay accurate
+        let quaternion = if trace > 0.0 {
+            let s = (trace + 1.0).sqrt() * 2.0;
+            Quaternion { x: (m(2, 1) - m(1, 2)) / s, y: (m(0, 2) - m(2, 0)) / s, z: (m(1, 0) - m(0, 1)) / s, w: s / 4.0 }
+        } else if m(0, 0) > m(1, 1) && m(0, 0) > m(2, 2) {
+            let s = (1.0 + m(0, 0) - m(1, 1) - m(2, 2)).sqrt() * 2.0;
+            Quaternion { x: s / 4.0, y: (m(0, 1) + m(1, 0)) / s, z: (m(0, 2) + m(2, 0)) / s, w: (m(2, 1) - m(1, 2)) / s }
+        } else if m(1, 1) > m(2, 2) {
+            let s = (1.0 + m(1, 1) - m(0, 0) - m(2, 2)).sqrt() * 2.0;
+            Quaternion { x: (m(0, 1) + m(1, 0)) / s, y: s / 4.0, z: (m(1, 2) + m(2, 1)) / s, w: (m(0, 2) - m(2, 0)) / s }
+        } else {
+            let s = (1.0 + m(2, 2) - m(0, 0) - m(1, 1)).sqrt() * 2.0;
+            Quaternion { x: (m(0, 2) + m(2, 0)) / s, y: (m(1, 2) + m(2, 1)) / s, z: s / 4.0, w: (m(1, 0) - m(0, 1)) / s }
+        };
+
+        quaternion.normalize().unwrap_or_else(Quaternion::identity)
+    }
+
+    pub fn to_matrix(&self) -> Matrix4 {
+        let Quaternion { x, y, z, w } = *self;
+
+        Matrix4 {
+            values: [
+                1.0 - 2.0 * (y * y + z * z), 2.0 * (x * y - z * w), 2.0 * (x * z + y * w), 0.0,
+                2.0 * (x * y + z * w), 1.0 - 2.0 * (x * x + z * z), 2.0 * (y * z - x * w), 0.0,
+                2.0 * (x * z - y * w), 2.0 * (y * z + x * w), 1.0 - 2.0 * (x * x + y * y), 0.0,
+                0.0, 0.0, 0.0, 1.0,
+            ],
+        }
+    }
+
+    pub fn dot(self, other: Quaternion) -> f32 {
+        self.x * other.x + self.y * other.y + self.z * other.z + self.w * other.w
+    }
+
+    pub fn normalize(self) -> Option<Quaternion> {
+        let length = self.dot(self).sqrt();
+        if length <= EPSILON {
+            return None;
+        }
+
+        Some(self * (1.0 / length))
+    }
+
+    // Linear blend renormalized, cheaper than slerp but not at constant speed.
+    // Both interpolations take the shorter way around
+    pub fn nlerp(self, other: Quaternion, t: f32) -> Quaternion {
+        let other = if self.dot(other) < 0.0 { -other } else { other };
+
+        (self * (1.0 - t) + other * t).normalize().unwrap_or(self)
+    }
+
+    pub fn slerp(self, other: Quaternion, t: f32) -> Quaternion {
+        let mut cos = self.dot(other);
+        let other = if cos < 0.0 {
+            cos = -cos;
+            -other
+        } else {
+            other
+        };
+
+        // Nearly equal rotations would divide by a sine close to zero
+        if cos > 0.9995 {
+            return self.nlerp(other, t);
+        }
+
+        let angle = cos.acos();
+        let sin = angle.sin();
+
+        self * (((1.0 - t) * angle).sin() / sin) + other * ((t * angle).sin() / sin)
+    }
+}
+
+impl Add for Quaternion {
+    type Output = Quaternion;
+
+    fn add(self, other: Quaternion) -> Quaternion {
+        Quaternion { x: self.x + other.x, y: self.y + other.y, z: self.z + other.z, w: self.w + other.w }
+    }
+}
+
+impl Mul<f32> for Quaternion {
+    type Output = Quaternion;
+
+    fn mul(self, scale: f32) -> Quaternion {
+        Quaternion { x: self.x * scale, y: self.y * scale, z: self.z * scale, w: self.w * scale }
+    }
+}
+
+// Rotation by other followed by self, like multiplying their matrices
+impl Mul for Quaternion {
+    type Output = Quaternion;
+
+    fn mul(self, other: Quaternion) -> Quaternion {
+        Quaternion {
+            x: self.w * other.x + self.x * other.w + self.y * other.z - self.z * other.y,
+            y: self.w * other.y - self.x * other.z + self.y * other.w + self.z * other.x,
+            z: self.w * other.z + self.x * other.y - self.y * other.x + self.z * other.w,
+            w: self.w * other.w - self.x * other.x - self.y * other.y - self.z * other.z,
+        }
+    }
+}
+
+impl Neg for Quaternion {
+    type Output = Quaternion;
+
+    fn neg(self) -> Quaternion {
+        Quaternion { x: -self.x, y: -self.y, z: -self.z, w: -self.w }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(a: f32, b: f32) {
+        assert!((a - b).abs() < 1e-5, "{} != {}", a, b);
+    }
+
+    fn assert_matrix(matrix: Matrix4, expected: [f32; 16]) {
+        for (a, b) in matrix.values.iter().zip(expected.iter()) {
+            assert_close(*a, *b);
+        }
+    }
+
+    fn matrix() -> Matrix4 {
+        Matrix4 {
+            values: [
+                2.0, 0.0, 0.0, 1.0,
+                0.0, 0.0, -3.0, 2.0,
+                0.0, 4.0, 0.0, 3.0,
+                0.0, 0.0, 0.0, 1.0,
+            ],
+        }
+    }
+
+    #[test]
+    fn multiply() {
+        let a = Matrix4 { values: [1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0, 8.0, 9.0, 10.0, 11.0, 12.0, 13.0, 14.0, 15.0, 16.0] };
+        let b = a.transpose();
+        assert_matrix(a * b, [
+            30.0, 70.0, 110.0, 150.0,
+            70.0, 174.0, 278.0, 382.0,
+            110.0, 278.0, 446.0, 614.0,
+            150.0, 382.0, 614.0, 846.0,
+        ]);
+        assert_matrix(a * Matrix4::identity(), a.values);
+    }
+
+    #[test]
+    fn determinant() {
+        assert_close(Matrix4::identity().determinant(), 1.0);
+        assert_close(matrix().determinant(), 24.0);
+
+        let singular = Matrix4 { values: [1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0, 8.0, 9.0, 10.0, 11.0, 12.0, 13.0, 14.0, 15.0, 16.0] };
+        assert_close(singular.determinant(), 0.0);
+        assert!(singular.inverse().is_none());
+    }
+
+    #[test]
+    fn inverse_of_small_scale() {
+        // Centimeters to meters, the determinant is tiny but the matrix is far from singular
+        let mut scale = Matrix4::identity();
+        scale.scale([0.01, 0.01, 0.01]);
+        let inverse = scale.inverse().unwrap();
+        assert_matrix(scale * inverse, Matrix4::identity().values);
+
+        // Rounding leaves a determinant far from zero when the values are large
+        let mut singular = Matrix4 { values: [1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0, 8.0, 9.0, 10.0, 11.0, 12.0, 13.0, 14.0, 15.0, 16.0] };
+        singular.values.iter_mut().for_each(|value| *value = *value * 1000.0 + 0.1);
+        assert!(singular.inverse().is_none());
+    }
+
+    #[test]
+    fn inverse() {
+        let inverse = matrix().inverse().unwrap();
+        assert_matrix(inverse, [
+            0.5, 0.0, 0.0, -0.5,
+            0.0, 0.0, 0.25, -0.75,
+            0.0, -1.0 / 3.0, 0.0, 2.0 / 3.0,
+            0.0, 0.0, 0.0, 1.0,
+        ]);
+        assert_matrix(matrix() * inverse, Matrix4::identity().values);
+    }
+
+    #[test]
+    fn transpose_in_place() {
+        let mut transposed = matrix();
+        transposed.transpose_in_place();
+        assert_matrix(transposed, matrix().transpose().values);
+        assert_close(transposed.values[12], 1.0);
+        assert_close(transposed.values[3], 0.0);
+    }
+
+    #[test]
+    fn transform() {
+        let point = matrix().transform_point(Vector3 { x: 1.0, y: 1.0, z: 1.0 });
+        assert_close(point.x, 3.0);
+        assert_close(point.y, -1.0);
+        assert_close(point.z, 7.0);
+
+        let direction = matrix().transform_direction(Vector3 { x: 1.0, y: 1.0, z: 1.0 });
+        assert_close(direction.x, 2.0);
+        assert_close(direction.y, -3.0);
+        assert_close(direction.z, 4.0);
+    }
+
+    #[test]
+    fn vector_arithmetic() {
+        let a = Vector3 { x: 1.0, y: 2.0, z: 3.0 };
+        let b = Vector3 { x: 4.0, y: 5.0, z: 6.0 };
+        assert_close(a.dot(b), 32.0);
+
+        let cross = a.cross(b);
+        assert_close(cross.x, -3.0);
+        assert_close(cross.y, 6.0);
+        assert_close(cross.z, -3.0);
+
+        let lerp = a.lerp(b, 0.5);
+        assert_close(lerp.x, 2.5);
+        assert_close(lerp.y, 3.5);
+        assert_close(lerp.z, 4.5);
+
+        let normal = Vector3 { x: 3.0, y: 0.0, z: 4.0 }.normalize().unwrap();
+        assert_close(normal.x, 0.6);
+        assert_close(normal.z, 0.8);
+        assert!(Vector3 { x: 0.0, y: 0.0, z: 0.0 }.normalize().is_none());
+
+        let a = Vector2 { x: 3.0, y: 4.0 };
+        let b = Vector2 { x: 1.0, y: 0.0 };
+        assert_close(a.length(), 5.0);
+        assert_close(a.dot(b), 3.0);
+        assert_close(b.cross(a), 4.0);
+        assert_close((a - b).x, 2.0);
+
+        let a = Vector4 { x: 1.0, y: 2.0, z: 2.0, w: 4.0 };
+        assert_close(a.length(), 5.0);
+        assert_close(a.normalize().unwrap().w, 0.8);
+        assert_close(a.lerp(-a, 0.25).y, 1.0);
+    }
+
+    fn assert_rotation(rotation: Quaternion, axis: Vector3, angle: f32) {
+        let expected = Quaternion::from_axis_angle(axis, angle);
+        assert_close(rotation.dot(expected).abs(), 1.0);
+    }
+
+    #[test]
+    fn quaternion_matrix() {
+        let z = Vector3 { x: 0.0, y: 0.0, z: 1.0 };
+        let rotation = Quaternion::from_axis_angle(z, std::f32::consts::FRAC_PI_2);
+        assert_matrix(rotation.to_matrix(), [
+            0.0, -1.0, 0.0, 0.0,
+            1.0, 0.0, 0.0, 0.0,
+            0.0, 0.0, 1.0, 0.0,
+            0.0, 0.0, 0.0, 1.0,
+        ]);
+
+        // Angles above 90 degrees take the other branches of from_matrix
+        for (axis, angle) in [(z, 0.5), (Vector3 { x: 1.0, y: 0.0, z: 0.0 }, 3.0), (Vector3 { x: 0.0, y: 1.0, z: 0.0 }, 3.0), (z, 3.0), (Vector3 { x: 1.0, y: 2.0, z: 3.0 }, 2.0)] {
+            let rotation = Quaternion::from_axis_angle(axis, angle);
+            assert_rotation(Quaternion::from_matrix(&rotation.to_matrix()), axis, angle);
+        }
+
+        let x = Quaternion::from_axis_angle(Vector3 { x: 1.0, y: 0.0, z: 0.0 }, 1.0);
+        let y = Quaternion::from_axis_angle(Vector3 { x: 0.0, y: 1.0, z: 0.0 }, 2.0);
+        assert_matrix((x * y).to_matrix(), (x.to_matrix() * y.to_matrix()).values);
+    }
+
+    #[test]
+    fn quaternion_interpolation() {
+        let z = Vector3 { x: 0.0, y: 0.0, z: 1.0 };
+        let a = Quaternion::identity();
+        let b = Quaternion::from_axis_angle(z, std::f32::consts::FRAC_PI_2);
+
+        assert_rotation(a.slerp(b, 0.5), z, std::f32::consts::FRAC_PI_4);
+        assert_rotation(a.slerp(b, 0.25), z, std::f32::consts::FRAC_PI_8);
+        assert_rotation(a.nlerp(b, 0.5), z, std::f32::consts::FRAC_PI_4);
+        assert_rotation(a.slerp(b, 1.0), z, std::f32::consts::FRAC_PI_2);
+
+        // The negated quaternion is the same rotation and must not take the long way
+        assert_rotation(a.slerp(-b, 0.5), z, std::f32::consts::FRAC_PI_4);
+    }
+
+    #[test]
+    fn decompose() {
+        let translation = Vector3 { x: 1.0, y: 2.0, z: 3.0 };
+        let rotation = Quaternion::from_axis_angle(Vector3 { x: 1.0, y: 1.0, z: 0.0 }, 0.7);
+
+        for scale in [Vector3 { x: 2.0, y: 3.0, z: 4.0 }, Vector3 { x: 2.0, y: -3.0, z: 4.0 }] {
+            let matrix = Matrix4::from_trs(translation, rotation, scale);
+            let (t, r, s) = matrix.decompose();
+
+            assert_close(t.x, 1.0);
+            assert_close(t.y, 2.0);
+            assert_close(t.z, 3.0);
+            assert_close(s.x.abs(), 2.0);
+            assert_close(s.y.abs(), 3.0);
+            assert_close(s.z.abs(), 4.0);
+            assert_matrix(Matrix4::from_trs(t, r, s), matrix.values);
+        }
+
+        let (_, _, scale) = Matrix4::from_trs(translation, rotation, Vector3 { x: 1.0, y: 1.0, z: -1.0 }).decompose();
+        assert!(scale.x * scale.y * scale.z < 0.0);
+        assert_close(matrix().decompose().2.x, 2.0);
     }
 }